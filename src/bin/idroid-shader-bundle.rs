@@ -0,0 +1,119 @@
+//! 离线生成 iOS 与 wasm 上使用的 `shader-preprocessed-wgsl`
+//!
+//! 遍历 `shader-wgsl/` 下所有 `.wgsl` 及 GLSL 文件，展开 `#include` 后按 `/` → `_` 的命名规则写入输出目录，
+//! 如 `shader-wgsl/fluid/collide.wgsl` → `shader-preprocessed-wgsl/fluid_collide.wgsl`；`.spv` 文件原样复制。
+//! 条件编译与 `#insert_code_segment` 标记会原样保留，运行时由 `idroid::shader` 再处理一遍，
+//! 与桌面端的处理结果一致。
+//!
+//! 用法：
+//!   idroid-shader-bundle [--validate] [-D NAME[=VALUE]]... [--exclude DIR]... <shader-wgsl> <output>
+
+use idroid::shader::{bundle_shaders, BundleOptions};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const USAGE: &str =
+    "Usage: idroid-shader-bundle [--validate] [-D NAME[=VALUE]]... [--exclude DIR]... <shader-wgsl> <output>";
+
+fn main() -> ExitCode {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{message}\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let bundled = match bundle_shaders(&options) {
+        Ok(bundled) => bundled,
+        Err(e) => {
+            eprintln!("Unable to bundle {}: {}", options.input.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut failed = 0;
+    for shader in bundled.iter() {
+        // 预处理失败时没有输出文件
+        if shader.output.exists() {
+            println!("{} -> {}", shader.path.display(), shader.output.display());
+        }
+        if shader.skipped_validation {
+            println!("  skip validation: requires code segments at runtime");
+        }
+        if let Some(e) = &shader.error {
+            eprintln!("error: {e}");
+            failed += 1;
+        }
+    }
+
+    if failed > 0 {
+        eprintln!("{failed} of {} shaders failed", bundled.len());
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<BundleOptions, String> {
+    let mut paths: Vec<PathBuf> = vec![];
+    let mut validate = false;
+    let mut defines = vec![];
+    let mut excludes = vec![];
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--validate" => validate = true,
+            "-D" => {
+                let define = args.next().ok_or("missing value for -D")?;
+                let (name, value) = define.split_once('=').unwrap_or((&define, ""));
+                defines.push((name.to_string(), value.to_string()));
+            }
+            "--exclude" => excludes.push(PathBuf::from(args.next().ok_or("missing value for --exclude")?)),
+            "-h" | "--help" => return Err(String::new()),
+            _ if arg.starts_with('-') => return Err(format!("unknown option: {arg}")),
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    if paths.len() != 2 {
+        return Err("expected <shader-wgsl> and <output> directories".to_string());
+    }
+    let output = paths.pop().unwrap();
+    let input = paths.pop().unwrap();
+    Ok(BundleOptions { input, output, validate, defines, excludes })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<BundleOptions, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_options() {
+        let options =
+            parse(&["-D", "WORKGROUP_SIZE=16", "--validate", "-D", "FAST", "--exclude", "func", "shader-wgsl", "out"])
+                .unwrap();
+        assert_eq!(
+            options,
+            BundleOptions {
+                input: PathBuf::from("shader-wgsl"),
+                output: PathBuf::from("out"),
+                validate: true,
+                defines: vec![("WORKGROUP_SIZE".to_string(), "16".to_string()), ("FAST".to_string(), String::new())],
+                excludes: vec![PathBuf::from("func")],
+            }
+        );
+        assert!(!parse(&["shader-wgsl", "out"]).unwrap().validate);
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        assert_eq!(parse(&["shader-wgsl", "out", "-D"]).unwrap_err(), "missing value for -D");
+        assert_eq!(parse(&["shader-wgsl", "out", "--exclude"]).unwrap_err(), "missing value for --exclude");
+        assert_eq!(parse(&["--fast", "shader-wgsl", "out"]).unwrap_err(), "unknown option: --fast");
+        assert!(parse(&["shader-wgsl"]).is_err());
+        assert!(parse(&["a", "b", "c"]).is_err());
+        assert_eq!(parse(&["--help"]).unwrap_err(), "");
+    }
+}
@@ -1,3 +1,4 @@
+use crate::memory::MemoryAllocation;
 use bytemuck::Pod;
 use wgpu::util::DeviceExt;
 
@@ -7,6 +8,8 @@ pub struct BufferObj {
     pub min_binding_size: Option<wgpu::BufferSize>,
     pub has_dynamic_offset: bool,
     pub read_only: bool,
+    /// 开启显存统计时记录此 buffer 的占用
+    pub allocation: MemoryAllocation,
 }
 
 #[allow(dead_code)]
@@ -18,33 +21,42 @@ impl BufferObj {
         BufferObj::create_buffer(device, Some(slice), None, wgpu::BufferUsages::STORAGE, label)
     }
 
-    pub fn create_empty_storage_buffer(
-        device: &wgpu::Device, size: wgpu::BufferAddress, can_read_back: bool, label: Option<&'static str>,
+    /// 内容为 0 的 buffer，大小向上对齐到 `wgpu::COPY_BUFFER_ALIGNMENT`，总是带有 COPY_DST
+    pub fn create_empty_buffer(
+        device: &wgpu::Device, size: wgpu::BufferAddress, usage: wgpu::BufferUsages, label: Option<&'static str>,
     ) -> Self {
+        let size = (size + wgpu::COPY_BUFFER_ALIGNMENT - 1) / wgpu::COPY_BUFFER_ALIGNMENT * wgpu::COPY_BUFFER_ALIGNMENT;
         let buffer = device.create_buffer(&wgpu::BufferDescriptor {
             size,
-            usage: if can_read_back {
-                wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC
-            } else {
-                wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST
-            },
+            usage: usage | wgpu::BufferUsages::COPY_DST,
             label,
             mapped_at_creation: false,
         });
-        BufferObj { buffer, size, min_binding_size: None, has_dynamic_offset: false, read_only: false }
+        let allocation = MemoryAllocation::buffer(label, size);
+        BufferObj { buffer, size, min_binding_size: None, has_dynamic_offset: false, read_only: false, allocation }
+    }
+
+    pub fn create_empty_storage_buffer(
+        device: &wgpu::Device, size: wgpu::BufferAddress, can_read_back: bool, label: Option<&'static str>,
+    ) -> Self {
+        let usage = if can_read_back {
+            wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC
+        } else {
+            wgpu::BufferUsages::STORAGE
+        };
+        BufferObj::create_empty_buffer(device, size, usage, label)
     }
 
     pub fn create_empty_dynamic_uniform_buffer(
         device: &wgpu::Device, size: wgpu::BufferAddress, min_binding_size: Option<wgpu::BufferSize>,
         label: Option<&'static str>,
     ) -> Self {
-        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            size,
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            label,
-            mapped_at_creation: false,
-        });
-        BufferObj { buffer, size, min_binding_size, has_dynamic_offset: true, read_only: true }
+        BufferObj {
+            min_binding_size,
+            has_dynamic_offset: true,
+            read_only: true,
+            ..BufferObj::create_empty_buffer(device, size, wgpu::BufferUsages::UNIFORM, label)
+        }
     }
 
     pub fn create_uniform_buffer<T>(device: &wgpu::Device, uniform: &T, label: Option<&'static str>) -> Self
@@ -83,6 +95,7 @@ impl BufferObj {
             contents: data,
             usage: usage | wgpu::BufferUsages::COPY_DST,
         });
-        BufferObj { buffer, size, min_binding_size: None, has_dynamic_offset: false, read_only: false }
+        let allocation = MemoryAllocation::buffer(label, size);
+        BufferObj { buffer, size, min_binding_size: None, has_dynamic_offset: false, read_only: false, allocation }
     }
 }
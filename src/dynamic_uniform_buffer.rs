@@ -0,0 +1,161 @@
+use crate::{BufferError, BufferObj};
+use bytemuck::Pod;
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+/// 动态 uniform buffer：每个元素按 `min_uniform_buffer_offset_alignment` 对齐存放，
+/// 通过 [`DynamicUniformBuffer::offset`] 得到第 `index` 个元素的 `DynamicOffset`
+pub struct DynamicUniformBuffer<T: Pod> {
+    obj: BufferObj,
+    stride: wgpu::BufferAddress,
+    capacity: usize,
+    _marker: PhantomData<T>,
+}
+
+#[allow(dead_code)]
+impl<T: Pod> DynamicUniformBuffer<T> {
+    /// 可容纳 `capacity` 个元素、内容为 0 的 buffer
+    pub fn new(device: &wgpu::Device, capacity: usize, label: Option<&'static str>) -> Self {
+        let stride = Self::aligned_stride(device);
+        let obj = BufferObj::create_empty_dynamic_uniform_buffer(
+            device,
+            stride * capacity.max(1) as wgpu::BufferAddress,
+            wgpu::BufferSize::new(std::mem::size_of::<T>() as wgpu::BufferAddress),
+            label,
+        );
+        DynamicUniformBuffer { obj, stride, capacity, _marker: PhantomData }
+    }
+
+    pub fn from_items(device: &wgpu::Device, items: &[T], label: Option<&'static str>) -> Self {
+        let stride = Self::aligned_stride(device);
+        let contents = pad_items(items, stride);
+        let obj = BufferObj {
+            min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<T>() as wgpu::BufferAddress),
+            has_dynamic_offset: true,
+            read_only: true,
+            ..BufferObj::create_buffer(device, Some(contents.as_slice()), None, wgpu::BufferUsages::UNIFORM, label)
+        };
+        DynamicUniformBuffer { obj, stride, capacity: items.len(), _marker: PhantomData }
+    }
+
+    /// 元素大小向上对齐到 `min_uniform_buffer_offset_alignment`
+    pub fn aligned_stride(device: &wgpu::Device) -> wgpu::BufferAddress {
+        let alignment = device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+        aligned_stride(std::mem::size_of::<T>() as wgpu::BufferAddress, alignment)
+    }
+
+    /// 相邻两个元素之间的字节数
+    pub fn stride(&self) -> wgpu::BufferAddress {
+        self.stride
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// 第 `index` 个元素的动态偏移
+    pub fn offset(&self, index: usize) -> wgpu::DynamicOffset {
+        (self.stride * index as wgpu::BufferAddress) as wgpu::DynamicOffset
+    }
+
+    pub fn buffer_obj(&self) -> &BufferObj {
+        &self.obj
+    }
+
+    pub fn write(&self, queue: &wgpu::Queue, index: usize, item: &T) -> Result<(), BufferError> {
+        self.write_slice(queue, index, std::slice::from_ref(item))
+    }
+
+    /// 从第 0 个元素开始，一次写入所有 `items`
+    pub fn write_all(&self, queue: &wgpu::Queue, items: &[T]) -> Result<(), BufferError> {
+        self.write_slice(queue, 0, items)
+    }
+
+    /// 从第 `index` 个元素开始写入 `items`，每个元素按 stride 补齐后一次提交
+    pub fn write_slice(&self, queue: &wgpu::Queue, index: usize, items: &[T]) -> Result<(), BufferError> {
+        let contents = write_contents(self.capacity, self.stride, index, items)?;
+        if contents.is_empty() {
+            return Ok(());
+        }
+        queue.write_buffer(&self.obj.buffer, self.offset(index) as wgpu::BufferAddress, &contents);
+        Ok(())
+    }
+}
+
+impl<T: Pod> Deref for DynamicUniformBuffer<T> {
+    type Target = BufferObj;
+    fn deref(&self) -> &BufferObj {
+        &self.obj
+    }
+}
+
+fn pad_items<T: Pod>(items: &[T], stride: wgpu::BufferAddress) -> Vec<u8> {
+    let mut contents = vec![0_u8; stride as usize * items.len().max(1)];
+    for (index, item) in items.iter().enumerate() {
+        let bytes = bytemuck::bytes_of(item);
+        let start = index * stride as usize;
+        contents[start..start + bytes.len()].copy_from_slice(bytes);
+    }
+    contents
+}
+
+fn aligned_stride(size: wgpu::BufferAddress, alignment: wgpu::BufferAddress) -> wgpu::BufferAddress {
+    ((size + alignment - 1) / alignment).max(1) * alignment
+}
+
+// 从第 `index` 个元素开始写入 `items` 时提交的数据，没有元素时为空
+fn write_contents<T: Pod>(
+    capacity: usize, stride: wgpu::BufferAddress, index: usize, items: &[T],
+) -> Result<Vec<u8>, BufferError> {
+    if index.checked_add(items.len()).map_or(true, |end| end > capacity) {
+        return Err(BufferError::OutOfBounds { offset: index, count: items.len(), len: capacity });
+    }
+    if items.is_empty() {
+        return Ok(vec![]);
+    }
+    let mut contents = pad_items(items, stride);
+    // 最后一个元素不需要补齐，但写入的大小需要是 4 的倍数
+    let tail = (stride as usize - std::mem::size_of::<T>()) / 4 * 4;
+    contents.truncate(contents.len() - tail);
+    Ok(contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aligns_stride() {
+        assert_eq!(aligned_stride(12, 256), 256);
+        assert_eq!(aligned_stride(256, 256), 256);
+        assert_eq!(aligned_stride(260, 256), 512);
+        // 零大小的类型也至少占用一个对齐单位
+        assert_eq!(aligned_stride(0, 256), 256);
+    }
+
+    #[test]
+    fn pads_items_to_stride() {
+        let contents = write_contents(4, 16, 1, &[[1_u8; 6], [2_u8; 6]]).unwrap();
+        // 第一个元素补齐到 stride，最后一个元素只补齐到 4 的倍数
+        let mut expected = vec![1_u8; 6];
+        expected.extend([0; 10]);
+        expected.extend([2; 6]);
+        expected.extend([0; 2]);
+        assert_eq!(contents, expected);
+        assert!(write_contents::<u32>(4, 16, 4, &[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn checks_write_bounds() {
+        assert_eq!(write_contents(4, 16, 3, &[1_u32]).unwrap().len(), 4);
+        assert_eq!(
+            write_contents(4, 16, 3, &[1_u32, 2]),
+            Err(BufferError::OutOfBounds { offset: 3, count: 2, len: 4 })
+        );
+        // index + 元素个数溢出时不能回绕成一个合法的范围
+        assert_eq!(
+            write_contents(4, 16, usize::MAX, &[1_u32]),
+            Err(BufferError::OutOfBounds { offset: usize::MAX, count: 1, len: 4 })
+        );
+    }
+}
@@ -0,0 +1,275 @@
+use crate::{BufferError, BufferObj};
+use bytemuck::Pod;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// [`FrameRingBuffer`] 的使用统计
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameRingStats {
+    /// 每帧可用的字节数
+    pub frame_capacity: wgpu::BufferAddress,
+    /// 当前帧已使用的字节数（含对齐填充）
+    pub used: wgpu::BufferAddress,
+    /// 各帧使用量的峰值
+    pub peak_used: wgpu::BufferAddress,
+    /// 当前帧的分配次数
+    pub allocations: u32,
+    /// 因空间不足而失败的分配次数
+    pub overflows: u32,
+    /// 因下一个区域仍在被 GPU 使用而失败的 `begin_frame` 次数
+    pub stalls: u32,
+}
+
+/// 按帧划分的环形 uniform / storage buffer
+///
+/// buffer 被分为 `frames_in_flight` 个区域，每帧在一个区域内按偏移对齐要求线性分配，
+/// 返回的偏移用于动态绑定。[`FrameRingBuffer::begin_frame`] 切换到下一个区域，
+/// 下一个区域仍在被 GPU 使用时返回 `BufferError::FrameInFlight`，不会覆盖其中的数据
+pub struct FrameRingBuffer {
+    obj: BufferObj,
+    slots: FrameSlots,
+    // 每个区域的 GPU 工作是否还未完成，由 `on_submitted_work_done` 回调清除
+    in_flight: Vec<Arc<AtomicBool>>,
+    // 当前区域是否已经注册了回调
+    submitted: bool,
+}
+
+#[allow(dead_code)]
+impl FrameRingBuffer {
+    /// `usage` 为 `UNIFORM` 或 `STORAGE`；`binding_size` 为单次分配的最大字节数，也是动态绑定的窗口大小
+    pub fn new(
+        device: &wgpu::Device, usage: wgpu::BufferUsages, frame_size: wgpu::BufferAddress,
+        binding_size: wgpu::BufferAddress, frames_in_flight: u32, label: Option<&'static str>,
+    ) -> Self {
+        let limits = device.limits();
+        let alignment = if usage.contains(wgpu::BufferUsages::STORAGE) {
+            limits.min_storage_buffer_offset_alignment
+        } else {
+            limits.min_uniform_buffer_offset_alignment
+        } as wgpu::BufferAddress;
+        let slots = FrameSlots::new(alignment, frame_size, binding_size, frames_in_flight);
+        let obj = BufferObj {
+            min_binding_size: wgpu::BufferSize::new(slots.binding_size),
+            has_dynamic_offset: true,
+            read_only: true,
+            ..BufferObj::create_empty_buffer(device, slots.buffer_size(), usage, label)
+        };
+        FrameRingBuffer {
+            obj,
+            in_flight: (0..slots.frames_in_flight).map(|_| Arc::new(AtomicBool::new(false))).collect(),
+            slots,
+            submitted: false,
+        }
+    }
+
+    pub fn create_uniform_ring(
+        device: &wgpu::Device, frame_size: wgpu::BufferAddress, binding_size: wgpu::BufferAddress,
+        frames_in_flight: u32, label: Option<&'static str>,
+    ) -> Self {
+        Self::new(device, wgpu::BufferUsages::UNIFORM, frame_size, binding_size, frames_in_flight, label)
+    }
+
+    /// 开始新的一帧，切换到下一个区域并清空其中的分配。需要在提交了使用当前区域的命令之后调用
+    ///
+    /// 区域内的 GPU 工作完成后才能再次使用，完成状态由 `queue.on_submitted_work_done` 得到，
+    /// 在原生平台上需要调用 `device.poll` 才会更新。下一个区域仍在使用时返回 `BufferError::FrameInFlight`，
+    /// 当前帧保持不变，可以 poll 之后重试
+    pub fn begin_frame(&mut self, queue: &wgpu::Queue) -> Result<(), BufferError> {
+        if !self.submitted {
+            let in_flight = self.in_flight[self.slots.frame_index as usize].clone();
+            in_flight.store(true, Ordering::Release);
+            queue.on_submitted_work_done(move || in_flight.store(false, Ordering::Release));
+            self.submitted = true;
+        }
+        let next_in_flight = self.in_flight[self.slots.next_frame() as usize].load(Ordering::Acquire);
+        self.slots.advance(next_in_flight)?;
+        self.submitted = false;
+        Ok(())
+    }
+
+    /// 写入 `item`，返回用于动态绑定的偏移
+    pub fn allocate<T: Pod>(&mut self, queue: &wgpu::Queue, item: &T) -> Result<wgpu::DynamicOffset, BufferError> {
+        self.allocate_bytes(queue, bytemuck::bytes_of(item))
+    }
+
+    pub fn allocate_slice<T: Pod>(
+        &mut self, queue: &wgpu::Queue, items: &[T],
+    ) -> Result<wgpu::DynamicOffset, BufferError> {
+        self.allocate_bytes(queue, bytemuck::cast_slice(items))
+    }
+
+    pub fn allocate_bytes(&mut self, queue: &wgpu::Queue, data: &[u8]) -> Result<wgpu::DynamicOffset, BufferError> {
+        let (offset, size) = self.slots.reserve(data.len() as wgpu::BufferAddress)?;
+        if size as usize == data.len() {
+            queue.write_buffer(&self.obj.buffer, offset, data);
+        } else {
+            // 写入的大小需要是 4 的倍数
+            let mut padded = data.to_vec();
+            padded.resize(size as usize, 0);
+            queue.write_buffer(&self.obj.buffer, offset, &padded);
+        }
+        Ok(offset as wgpu::DynamicOffset)
+    }
+
+    /// 当前帧剩余的字节数
+    pub fn remaining(&self) -> wgpu::BufferAddress {
+        self.slots.frame_size - self.slots.cursor
+    }
+
+    pub fn stats(&self) -> FrameRingStats {
+        self.slots.stats
+    }
+
+    pub fn frames_in_flight(&self) -> u32 {
+        self.slots.frames_in_flight
+    }
+
+    pub fn buffer_obj(&self) -> &BufferObj {
+        &self.obj
+    }
+}
+
+impl Deref for FrameRingBuffer {
+    type Target = BufferObj;
+    fn deref(&self) -> &BufferObj {
+        &self.obj
+    }
+}
+
+// 区域的划分与区域内的线性分配，不涉及 GPU 资源
+#[derive(Clone, Debug)]
+struct FrameSlots {
+    alignment: wgpu::BufferAddress,
+    frame_size: wgpu::BufferAddress,
+    binding_size: wgpu::BufferAddress,
+    frames_in_flight: u32,
+    frame_index: u32,
+    cursor: wgpu::BufferAddress,
+    stats: FrameRingStats,
+}
+
+impl FrameSlots {
+    fn new(
+        alignment: wgpu::BufferAddress, frame_size: wgpu::BufferAddress, binding_size: wgpu::BufferAddress,
+        frames_in_flight: u32,
+    ) -> Self {
+        let frame_size = align_to(frame_size.max(binding_size), alignment);
+        FrameSlots {
+            alignment,
+            frame_size,
+            binding_size: align_to(binding_size.max(1), wgpu::COPY_BUFFER_ALIGNMENT),
+            frames_in_flight: frames_in_flight.max(1),
+            frame_index: 0,
+            cursor: 0,
+            stats: FrameRingStats { frame_capacity: frame_size, ..Default::default() },
+        }
+    }
+
+    // 末尾多留一个窗口，保证最后一个区域中的偏移加上窗口大小不超出 buffer
+    fn buffer_size(&self) -> wgpu::BufferAddress {
+        self.frame_size * self.frames_in_flight as wgpu::BufferAddress + self.binding_size
+    }
+
+    fn next_frame(&self) -> u32 {
+        (self.frame_index + 1) % self.frames_in_flight
+    }
+
+    // 切换到下一个区域，`next_in_flight` 为该区域是否仍在被 GPU 使用
+    fn advance(&mut self, next_in_flight: bool) -> Result<(), BufferError> {
+        let next = self.next_frame();
+        if next_in_flight {
+            self.stats.stalls += 1;
+            return Err(BufferError::FrameInFlight { frame: next });
+        }
+        self.frame_index = next;
+        self.cursor = 0;
+        self.stats.used = 0;
+        self.stats.allocations = 0;
+        Ok(())
+    }
+
+    // 在当前区域中分配 `len` 字节，返回在 buffer 中的偏移及补齐到 4 的倍数后的大小
+    fn reserve(&mut self, len: wgpu::BufferAddress) -> Result<(wgpu::BufferAddress, wgpu::BufferAddress), BufferError> {
+        let size = align_to(len, wgpu::COPY_BUFFER_ALIGNMENT);
+        if size > self.binding_size {
+            return Err(BufferError::AllocationTooLarge { size, binding_size: self.binding_size });
+        }
+        let remaining = self.frame_size - self.cursor;
+        if size > remaining {
+            self.stats.overflows += 1;
+            return Err(BufferError::FrameOverflow { requested: size, remaining });
+        }
+        let offset = self.frame_index as wgpu::BufferAddress * self.frame_size + self.cursor;
+        self.cursor = align_to(self.cursor + size, self.alignment).min(self.frame_size);
+        self.stats.used = self.cursor;
+        self.stats.peak_used = self.stats.peak_used.max(self.cursor);
+        self.stats.allocations += 1;
+        Ok((offset, size))
+    }
+}
+
+fn align_to(value: wgpu::BufferAddress, alignment: wgpu::BufferAddress) -> wgpu::BufferAddress {
+    (value + alignment - 1) / alignment * alignment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_slot_sizes() {
+        let slots = FrameSlots::new(256, 300, 100, 0);
+        assert_eq!(slots.frames_in_flight, 1);
+        assert_eq!(slots.frame_size, 512);
+        assert_eq!(slots.binding_size, 100);
+        assert_eq!(slots.buffer_size(), 612);
+        // 每帧至少能容纳一次最大的分配
+        let slots = FrameSlots::new(256, 64, 1000, 3);
+        assert_eq!(slots.frame_size, 1024);
+        assert_eq!(slots.binding_size, 1000);
+        assert_eq!(slots.buffer_size(), 1024 * 3 + 1000);
+        assert_eq!(FrameSlots::new(256, 256, 0, 2).binding_size, 4);
+    }
+
+    #[test]
+    fn allocates_aligned_offsets_within_a_frame() {
+        let mut slots = FrameSlots::new(256, 1024, 256, 2);
+        assert_eq!(slots.reserve(10), Ok((0, 12)));
+        assert_eq!(slots.reserve(256), Ok((256, 256)));
+        assert_eq!(slots.reserve(4), Ok((512, 4)));
+        assert_eq!(slots.reserve(257), Err(BufferError::AllocationTooLarge { size: 260, binding_size: 256 }));
+        assert_eq!(slots.reserve(4), Ok((768, 4)));
+        assert_eq!(slots.reserve(4), Err(BufferError::FrameOverflow { requested: 4, remaining: 0 }));
+
+        let stats = slots.stats;
+        assert_eq!((stats.used, stats.peak_used, stats.allocations, stats.overflows), (1024, 1024, 4, 1));
+    }
+
+    #[test]
+    fn cycles_through_frames() {
+        let mut slots = FrameSlots::new(256, 512, 256, 3);
+        slots.reserve(16).unwrap();
+        for frame in [1, 2, 0, 1] {
+            slots.advance(false).unwrap();
+            assert_eq!(slots.frame_index, frame);
+            assert_eq!(slots.reserve(16), Ok((frame as wgpu::BufferAddress * 512, 16)));
+        }
+        assert_eq!(slots.stats.used, 256);
+        assert_eq!(slots.stats.allocations, 1);
+    }
+
+    #[test]
+    fn keeps_the_frame_while_the_next_one_is_in_flight() {
+        let mut slots = FrameSlots::new(256, 512, 256, 2);
+        slots.reserve(16).unwrap();
+        assert_eq!(slots.advance(true), Err(BufferError::FrameInFlight { frame: 1 }));
+        assert_eq!(slots.frame_index, 0);
+        // 当前帧的分配不受影响，可以继续分配
+        assert_eq!(slots.reserve(16), Ok((256, 16)));
+        assert_eq!(slots.stats.stalls, 1);
+        slots.advance(false).unwrap();
+        assert_eq!(slots.reserve(16), Ok((512, 16)));
+        assert_eq!(slots.stats.peak_used, 512);
+    }
+}
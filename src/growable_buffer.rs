@@ -0,0 +1,141 @@
+use crate::{BufferError, BufferObj};
+use bytemuck::Pod;
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+/// 容量不足时按倍数扩容的 buffer，类似 `Vec`
+///
+/// 扩容会创建新的 `wgpu::Buffer` 并使 [`GrowableBuffer::generation`] 加 1，
+/// 引用了旧 buffer 的 bind group 需要通过节点的 `refresh_bind_group` 重建
+pub struct GrowableBuffer<T: Pod> {
+    obj: BufferObj,
+    len: usize,
+    capacity: usize,
+    usage: wgpu::BufferUsages,
+    label: Option<&'static str>,
+    generation: u64,
+    _marker: PhantomData<T>,
+}
+
+#[allow(dead_code)]
+impl<T: Pod> GrowableBuffer<T> {
+    /// 可容纳 `capacity` 个元素的空 buffer
+    pub fn new(device: &wgpu::Device, capacity: usize, usage: wgpu::BufferUsages, label: Option<&'static str>) -> Self {
+        // 需要 COPY_SRC 才能在扩容时把旧内容复制到新 buffer
+        let usage = usage | wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC;
+        let capacity = capacity.max(1);
+        let obj = Self::create_buffer_obj(device, capacity, usage, label);
+        GrowableBuffer { obj, len: 0, capacity, usage, label, generation: 0, _marker: PhantomData }
+    }
+
+    pub fn create_storage_buffer(device: &wgpu::Device, capacity: usize, label: Option<&'static str>) -> Self {
+        Self::new(device, capacity, wgpu::BufferUsages::STORAGE, label)
+    }
+
+    /// 元素个数
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// 不扩容时可容纳的元素个数
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// 每次扩容后加 1
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn buffer_obj(&self) -> &BufferObj {
+        &self.obj
+    }
+
+    /// 只清空长度，不释放容量
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// 保证至少还能追加 `additional` 个元素，已有内容会复制到新 buffer。发生扩容时返回 true
+    pub fn reserve(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, additional: usize) -> bool {
+        self.grow(device, queue, self.len + additional, true)
+    }
+
+    /// 在末尾追加 `data`，发生扩容时返回 true
+    pub fn extend_from_slice(
+        &mut self, device: &wgpu::Device, queue: &wgpu::Queue, data: &[T],
+    ) -> Result<bool, BufferError> {
+        let offset = self.len;
+        Self::check_alignment(offset, data.len())?;
+        let grown = self.grow(device, queue, offset + data.len(), true);
+        self.write_at(queue, offset, data);
+        self.len = offset + data.len();
+        Ok(grown)
+    }
+
+    /// 用 `data` 替换全部内容，旧内容不会被复制。发生扩容时返回 true
+    pub fn replace(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, data: &[T]) -> Result<bool, BufferError> {
+        Self::check_alignment(0, data.len())?;
+        let grown = self.grow(device, queue, data.len(), false);
+        self.write_at(queue, 0, data);
+        self.len = data.len();
+        Ok(grown)
+    }
+
+    fn write_at(&self, queue: &wgpu::Queue, offset: usize, data: &[T]) {
+        if !data.is_empty() {
+            let byte_offset = (offset * std::mem::size_of::<T>()) as wgpu::BufferAddress;
+            queue.write_buffer(&self.obj.buffer, byte_offset, bytemuck::cast_slice(data));
+        }
+    }
+
+    fn check_alignment(offset: usize, count: usize) -> Result<(), BufferError> {
+        let element_size = std::mem::size_of::<T>() as wgpu::BufferAddress;
+        let byte_offset = offset as wgpu::BufferAddress * element_size;
+        let byte_size = count as wgpu::BufferAddress * element_size;
+        if byte_offset % wgpu::COPY_BUFFER_ALIGNMENT != 0 || byte_size % wgpu::COPY_BUFFER_ALIGNMENT != 0 {
+            return Err(BufferError::Misaligned { offset: byte_offset, size: byte_size });
+        }
+        Ok(())
+    }
+
+    fn grow(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, required: usize, keep_contents: bool) -> bool {
+        if required <= self.capacity {
+            return false;
+        }
+        let capacity = required.max(self.capacity * 2);
+        let obj = Self::create_buffer_obj(device, capacity, self.usage, self.label);
+        if keep_contents && self.len > 0 {
+            let mut encoder =
+                device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("grow buffer") });
+            let size = (self.len * std::mem::size_of::<T>()) as wgpu::BufferAddress;
+            // copy 的大小需要是 4 的倍数
+            let size =
+                (size + wgpu::COPY_BUFFER_ALIGNMENT - 1) / wgpu::COPY_BUFFER_ALIGNMENT * wgpu::COPY_BUFFER_ALIGNMENT;
+            encoder.copy_buffer_to_buffer(&self.obj.buffer, 0, &obj.buffer, 0, size.min(self.obj.size));
+            queue.submit(Some(encoder.finish()));
+        }
+        self.obj = obj;
+        self.capacity = capacity;
+        self.generation += 1;
+        true
+    }
+
+    fn create_buffer_obj(
+        device: &wgpu::Device, capacity: usize, usage: wgpu::BufferUsages, label: Option<&'static str>,
+    ) -> BufferObj {
+        let size = (capacity * std::mem::size_of::<T>()) as wgpu::BufferAddress;
+        BufferObj::create_empty_buffer(device, size, usage, label)
+    }
+}
+
+impl<T: Pod> Deref for GrowableBuffer<T> {
+    type Target = BufferObj;
+    fn deref(&self) -> &BufferObj {
+        &self.obj
+    }
+}
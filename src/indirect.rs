@@ -0,0 +1,68 @@
+use crate::BufferObj;
+use bytemuck::{Pod, Zeroable};
+
+/// `draw_indirect` 的参数，内存布局与 WebGPU 规范一致
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Pod, Zeroable)]
+pub struct DrawIndirectArgs {
+    pub vertex_count: u32,
+    pub instance_count: u32,
+    pub first_vertex: u32,
+    pub first_instance: u32,
+}
+
+/// `draw_indexed_indirect` 的参数
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Pod, Zeroable)]
+pub struct DrawIndexedIndirectArgs {
+    pub index_count: u32,
+    pub instance_count: u32,
+    pub first_index: u32,
+    pub base_vertex: i32,
+    pub first_instance: u32,
+}
+
+/// `dispatch_workgroups_indirect` 的参数，即 x, y, z 三个方向上的工作组数
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Pod, Zeroable)]
+pub struct DispatchIndirectArgs {
+    pub x: u32,
+    pub y: u32,
+    pub z: u32,
+}
+
+impl DrawIndirectArgs {
+    pub fn new(vertex_count: u32, instance_count: u32) -> Self {
+        DrawIndirectArgs { vertex_count, instance_count, first_vertex: 0, first_instance: 0 }
+    }
+}
+
+impl DrawIndexedIndirectArgs {
+    pub fn new(index_count: u32, instance_count: u32) -> Self {
+        DrawIndexedIndirectArgs { index_count, instance_count, first_index: 0, base_vertex: 0, first_instance: 0 }
+    }
+}
+
+impl DispatchIndirectArgs {
+    pub fn new(x: u32, y: u32, z: u32) -> Self {
+        DispatchIndirectArgs { x, y, z }
+    }
+}
+
+#[allow(dead_code)]
+impl BufferObj {
+    /// 存放间接绘制或 dispatch 参数的 buffer。同时带有 `STORAGE`，可以在 compute shader 中写入参数，
+    /// 此时着色器中对应的结构体成员需要与参数类型的字段一一对应
+    pub fn create_indirect_buffer<T>(device: &wgpu::Device, args: &[T], label: Option<&'static str>) -> Self
+    where
+        T: 'static + Pod + Copy,
+    {
+        BufferObj::create_buffer(
+            device,
+            Some(args),
+            None,
+            wgpu::BufferUsages::INDIRECT | wgpu::BufferUsages::STORAGE,
+            label,
+        )
+    }
+}
@@ -3,13 +3,28 @@ pub use app_surface::*;
 
 pub mod geometry;
 pub mod load_texture;
-pub use load_texture::AnyTexture;
+pub use load_texture::{AnyTexture, AssetPath, TextureError};
 pub mod math;
+pub mod memory;
 pub mod utils;
 pub use utils::{depth_stencil, matrix_helper};
 
 mod buffer;
 pub use buffer::BufferObj;
+mod typed_buffer;
+pub use typed_buffer::{BufferError, TypedBuffer};
+mod dynamic_uniform_buffer;
+pub use dynamic_uniform_buffer::DynamicUniformBuffer;
+mod growable_buffer;
+pub use growable_buffer::GrowableBuffer;
+mod frame_ring_buffer;
+pub use frame_ring_buffer::{FrameRingBuffer, FrameRingStats};
+mod indirect;
+pub use indirect::{DispatchIndirectArgs, DrawIndexedIndirectArgs, DrawIndirectArgs};
+mod readback;
+pub use readback::ReadbackFuture;
+#[cfg(test)]
+mod test_util;
 
 mod mvp_uniform_obj;
 pub use mvp_uniform_obj::{MVPUniform, MVPUniform2, MVPUniformObj};
@@ -1,41 +1,270 @@
+use crate::memory::MemoryAllocation;
 use image::GenericImageView;
-use std::{num::NonZeroU32, path::PathBuf};
+use std::{
+    num::NonZeroU32,
+    path::{Path, PathBuf},
+};
 use wgpu::{Extent3d, Sampler, Texture, TextureFormat, TextureView};
 
+/// 纹理加载错误
+#[derive(Debug, Clone)]
+pub enum TextureError {
+    /// 无法读取图片文件
+    Io { path: PathBuf, reason: String },
+    /// 图片解码失败，由内存中的数据解码时 `path` 为 None
+    Decode { path: Option<PathBuf>, reason: String },
+    /// 不支持的图片颜色类型
+    UnsupportedColorType { path: Option<PathBuf>, color_type: image::ColorType },
+    /// 宽高为 0，或超出了 `device.limits().max_texture_dimension_2d`
+    InvalidDimensions { width: u32, height: u32, max: u32 },
+    /// 纹理格式不支持所需的 usage，如 R8Unorm 不能用作 storage 纹理
+    IncompatibleUsage { format: TextureFormat, usage: wgpu::TextureUsages },
+    /// 图片无法转换为指定的纹理格式
+    UnsupportedFormat { format: TextureFormat },
+    /// 纹理格式需要 device 开启的 features，如 R16Unorm 需要 `TEXTURE_FORMAT_16BIT_NORM`
+    MissingFeatures { format: TextureFormat, features: wgpu::Features },
+    /// 更新纹理时，图片的尺寸与纹理不一致
+    SizeMismatch { expected: Extent3d, actual: Extent3d },
+    /// 更新纹理时，图片对应的纹理格式与纹理不一致
+    FormatMismatch { expected: TextureFormat, actual: TextureFormat },
+    /// 由 buffer 创建纹理时，buffer 的大小或每行字节数不满足拷贝要求
+    InvalidBufferLayout { reason: String },
+}
+
+impl std::fmt::Display for TextureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TextureError::Io { path, reason } => {
+                write!(f, "Unable to read {}: {}", path.display(), reason)
+            }
+            TextureError::Decode { path: Some(path), reason } => {
+                write!(f, "Unable to decode {}: {}", path.display(), reason)
+            }
+            TextureError::Decode { path: None, reason } => write!(f, "Unable to decode image: {reason}"),
+            TextureError::UnsupportedColorType { path: Some(path), color_type } => {
+                write!(f, "{} has an unsupported color type {:?}", path.display(), color_type)
+            }
+            TextureError::UnsupportedColorType { path: None, color_type } => {
+                write!(f, "Image has an unsupported color type {:?}", color_type)
+            }
+            TextureError::InvalidDimensions { width, height, max } => write!(
+                f,
+                "Texture size {width}x{height} must be non-zero and no larger than {max}"
+            ),
+            TextureError::IncompatibleUsage { format, usage } => {
+                write!(f, "Texture format {:?} does not support usage {:?}", format, usage)
+            }
+            TextureError::UnsupportedFormat { format } => {
+                write!(f, "Images cannot be converted to texture format {:?}", format)
+            }
+            TextureError::MissingFeatures { format, features } => {
+                write!(f, "Texture format {:?} requires device features {:?}", format, features)
+            }
+            TextureError::SizeMismatch { expected, actual } => write!(
+                f,
+                "Image size {}x{} does not match the texture size {}x{}",
+                actual.width, actual.height, expected.width, expected.height
+            ),
+            TextureError::FormatMismatch { expected, actual } => write!(
+                f,
+                "Image loads as texture format {:?}, but the texture is {:?}",
+                actual, expected
+            ),
+            TextureError::InvalidBufferLayout { reason } => {
+                write!(f, "Invalid buffer layout for texture copy: {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TextureError {}
+
+/// 纹理文件的位置
+#[derive(Clone, Copy, Debug)]
+pub enum AssetPath<'a> {
+    /// 相对于 app 资源中纹理目录的路径，由 `app_surface::fs::get_texture_file_path` 解析
+    Bundle(&'a str),
+    /// 文件系统中的路径，原样使用
+    Absolute(&'a Path),
+}
+
+impl<'a> AssetPath<'a> {
+    pub fn resolve(&self) -> PathBuf {
+        match self {
+            AssetPath::Bundle(name) => app_surface::fs::get_texture_file_path(name),
+            AssetPath::Absolute(path) => path.to_path_buf(),
+        }
+    }
+}
+
+/// 绝对路径按 `Absolute` 处理，其余按 `Bundle` 处理
+impl<'a> From<&'a str> for AssetPath<'a> {
+    fn from(path: &'a str) -> Self {
+        if Path::new(path).is_absolute() {
+            AssetPath::Absolute(Path::new(path))
+        } else {
+            AssetPath::Bundle(path)
+        }
+    }
+}
+
+impl<'a> From<&'a Path> for AssetPath<'a> {
+    fn from(path: &'a Path) -> Self {
+        AssetPath::Absolute(path)
+    }
+}
+
+impl<'a> From<&'a PathBuf> for AssetPath<'a> {
+    fn from(path: &'a PathBuf) -> Self {
+        AssetPath::Absolute(path.as_path())
+    }
+}
+
 pub struct AnyTexture {
     pub size: Extent3d,
     pub tex: Texture,
     pub tex_view: TextureView,
     pub format: TextureFormat,
     pub view_dimension: wgpu::TextureViewDimension,
+    /// 开启显存统计时记录此纹理的占用
+    pub allocation: MemoryAllocation,
 }
 #[allow(dead_code)]
-pub fn from_path(
-    image_path: &str,
+pub fn from_path<'a>(
+    image_path: impl Into<AssetPath<'a>>,
     app_view: &crate::AppSurface,
     usage: wgpu::TextureUsages,
     set_to_grayscale: bool,
-) -> (AnyTexture, Sampler) {
-    let path = if image_path.split("/").count() > 5 {
-        // is already a full path
-        PathBuf::from(image_path)
-    } else {
-        app_surface::fs::get_texture_file_path(image_path)
-    };
-    let (texels, texture_extent, format) = load_from_path(path, set_to_grayscale);
+) -> Result<(AnyTexture, Sampler), TextureError> {
+    let path = image_path.into().resolve();
+    let img = open_image(&path)?;
+    create_from_image(
+        img,
+        Some(&path),
+        None,
+        &app_view.device,
+        &app_view.queue,
+        usage,
+        set_to_grayscale,
+        None,
+    )
+}
+
+// 指定纹理格式，如将 16 位灰度图加载为 R16Float
+#[allow(dead_code)]
+pub fn from_path_with_format<'a>(
+    image_path: impl Into<AssetPath<'a>>,
+    app_view: &crate::AppSurface,
+    usage: wgpu::TextureUsages,
+    format: TextureFormat,
+) -> Result<(AnyTexture, Sampler), TextureError> {
+    let path = image_path.into().resolve();
+    let img = open_image(&path)?;
+    create_from_image(
+        img,
+        Some(&path),
+        None,
+        &app_view.device,
+        &app_view.queue,
+        usage,
+        false,
+        Some(format),
+    )
+}
+
+// 由宿主 app 传入的编码后的图片数据，如 png 文件的内容；`label` 用于调试及显存统计
+#[allow(dead_code)]
+pub fn from_bytes(
+    bytes: &[u8],
+    app_view: &crate::AppSurface,
+    usage: wgpu::TextureUsages,
+    set_to_grayscale: bool,
+    label: Option<&str>,
+) -> Result<(AnyTexture, Sampler), TextureError> {
+    let img = decode_bytes(bytes).map_err(|e| TextureError::Decode {
+        path: None,
+        reason: decode_error_reason(e),
+    })?;
+    create_from_image(
+        img,
+        None,
+        label,
+        &app_view.device,
+        &app_view.queue,
+        usage,
+        set_to_grayscale,
+        None,
+    )
+}
+
+#[allow(dead_code)]
+pub fn from_image(
+    img: image::DynamicImage,
+    app_view: &crate::AppSurface,
+    usage: wgpu::TextureUsages,
+    set_to_grayscale: bool,
+) -> Result<(AnyTexture, Sampler), TextureError> {
+    create_from_image(
+        img,
+        None,
+        None,
+        &app_view.device,
+        &app_view.queue,
+        usage,
+        set_to_grayscale,
+        None,
+    )
+}
+
+#[allow(dead_code)]
+pub fn from_image_with_format(
+    img: image::DynamicImage,
+    app_view: &crate::AppSurface,
+    usage: wgpu::TextureUsages,
+    format: TextureFormat,
+) -> Result<(AnyTexture, Sampler), TextureError> {
+    create_from_image(
+        img,
+        None,
+        None,
+        &app_view.device,
+        &app_view.queue,
+        usage,
+        false,
+        Some(format),
+    )
+}
+
+// 未指定 `label` 时使用图片路径，便于显存统计按资源分组
+#[allow(clippy::too_many_arguments)]
+fn create_from_image(
+    img: image::DynamicImage,
+    path: Option<&Path>,
+    label: Option<&str>,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    usage: wgpu::TextureUsages,
+    set_to_grayscale: bool,
+    format: Option<TextureFormat>,
+) -> Result<(AnyTexture, Sampler), TextureError> {
+    let (texels, texture_extent, format) =
+        image_texels(device, img, path, set_to_grayscale, format)?;
+    check_texture(device, format, texture_extent, usage)?;
     let pixel_bytes = single_pixel_bytes(format);
 
-    let texture = app_view.device.create_texture(&wgpu::TextureDescriptor {
+    let path_label = path.map(|p| p.to_string_lossy());
+    let tex_desc = wgpu::TextureDescriptor {
         size: texture_extent,
         mip_level_count: 1,
         sample_count: 1,
         dimension: wgpu::TextureDimension::D2,
         format,
         usage,
-        label: None,
-    });
+        label: label.or(path_label.as_deref()),
+    };
+    let texture = device.create_texture(&tex_desc);
     let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-    app_view.queue.write_texture(
+    queue.write_texture(
         wgpu::ImageCopyTexture {
             texture: &texture,
             mip_level: 0,
@@ -45,8 +274,8 @@ pub fn from_path(
         &texels,
         wgpu::ImageDataLayout {
             offset: 0,
-            bytes_per_row: Some(NonZeroU32::new(pixel_bytes * texture_extent.width).unwrap()),
-            rows_per_image: Some(NonZeroU32::new(texture_extent.height).unwrap()),
+            bytes_per_row: NonZeroU32::new(pixel_bytes * texture_extent.width),
+            rows_per_image: NonZeroU32::new(texture_extent.height),
         },
         texture_extent,
     );
@@ -56,26 +285,28 @@ pub fn from_path(
         tex_view: texture_view,
         view_dimension: wgpu::TextureViewDimension::D2,
         format,
+        allocation: MemoryAllocation::texture(&tex_desc),
     };
 
-    (any_tex, default_sampler(&app_view.device))
+    Ok((any_tex, default_sampler(device)))
 }
 
 // from webgpu spec: R8 | R16 is not supported for storage use.
 #[allow(dead_code)]
-pub fn into_format_r32float(
-    image_path: &str,
+pub fn into_format_r32float<'a>(
+    image_path: impl Into<AssetPath<'a>>,
     app_view: &crate::AppSurface,
     usage: wgpu::TextureUsages,
     label: Option<&'static str>,
-) -> AnyTexture {
-    let path = app_surface::fs::get_texture_file_path(image_path);
+) -> Result<AnyTexture, TextureError> {
+    let path = image_path.into().resolve();
 
-    let (texels, texture_extent) = load_by_luma(path);
+    let (texels, texture_extent) = load_by_luma(path)?;
     let pixel_bytes = 4;
     let new_texels: Vec<f32> = texels.into_iter().map(|t| t as f32).collect();
     let tex_format = TextureFormat::R32Float;
-    let texture = app_view.device.create_texture(&wgpu::TextureDescriptor {
+    check_texture(&app_view.device, tex_format, texture_extent, usage)?;
+    let tex_desc = wgpu::TextureDescriptor {
         size: texture_extent,
         mip_level_count: 1,
         sample_count: 1,
@@ -83,7 +314,8 @@ pub fn into_format_r32float(
         format: tex_format,
         usage,
         label,
-    });
+    };
+    let texture = app_view.device.create_texture(&tex_desc);
     let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
     app_view.queue.write_texture(
         wgpu::ImageCopyTexture {
@@ -95,8 +327,8 @@ pub fn into_format_r32float(
         bytemuck::cast_slice(&new_texels),
         wgpu::ImageDataLayout {
             offset: 0,
-            bytes_per_row: Some(NonZeroU32::new(pixel_bytes * texture_extent.width).unwrap()),
-            rows_per_image: Some(NonZeroU32::new(texture_extent.height).unwrap()),
+            bytes_per_row: NonZeroU32::new(pixel_bytes * texture_extent.width),
+            rows_per_image: NonZeroU32::new(texture_extent.height),
         },
         texture_extent,
     );
@@ -106,25 +338,41 @@ pub fn into_format_r32float(
         tex_view: texture_view,
         view_dimension: wgpu::TextureViewDimension::D2,
         format: tex_format,
+        allocation: MemoryAllocation::texture(&tex_desc),
     };
-    any_tex
+    Ok(any_tex)
 }
 
+// 图片的尺寸及对应的纹理格式需要与 `texture` 一致
 #[allow(dead_code)]
-pub fn update_by_path(
-    image_path: &str,
+pub fn update_by_path<'a>(
+    image_path: impl Into<AssetPath<'a>>,
     app_view: &crate::AppSurface,
-    texture: &Texture,
+    texture: &AnyTexture,
     set_to_grayscale: bool,
-) {
-    let path = app_surface::fs::get_texture_file_path(image_path);
+) -> Result<(), TextureError> {
+    let path = image_path.into().resolve();
 
-    let (texels, texture_extent, format) = load_from_path(path, set_to_grayscale);
+    let img = open_image(&path)?;
+    let (texels, texture_extent, format) =
+        image_texels(&app_view.device, img, Some(&path), set_to_grayscale, None)?;
+    if texture_extent != texture.size {
+        return Err(TextureError::SizeMismatch {
+            expected: texture.size,
+            actual: texture_extent,
+        });
+    }
+    if format != texture.format {
+        return Err(TextureError::FormatMismatch {
+            expected: texture.format,
+            actual: format,
+        });
+    }
     let pixel_bytes = single_pixel_bytes(format);
 
     app_view.queue.write_texture(
         wgpu::ImageCopyTexture {
-            texture,
+            texture: &texture.tex,
             mip_level: 0,
             origin: wgpu::Origin3d::ZERO,
             aspect: wgpu::TextureAspect::All,
@@ -132,11 +380,12 @@ pub fn update_by_path(
         &texels,
         wgpu::ImageDataLayout {
             offset: 0,
-            bytes_per_row: Some(NonZeroU32::new(pixel_bytes * texture_extent.width).unwrap()),
-            rows_per_image: Some(NonZeroU32::new(texture_extent.height).unwrap()),
+            bytes_per_row: NonZeroU32::new(pixel_bytes * texture_extent.width),
+            rows_per_image: NonZeroU32::new(texture_extent.height),
         },
         texture_extent,
     );
+    Ok(())
 }
 
 #[allow(dead_code)]
@@ -149,21 +398,25 @@ pub fn from_buffer(
     pixel_size: u32,
     format: TextureFormat,
     usage: wgpu::TextureUsages,
-) -> (AnyTexture, Sampler) {
+    label: Option<&str>,
+) -> Result<(AnyTexture, Sampler), TextureError> {
     let texture_extent = wgpu::Extent3d {
         width,
         height,
         depth_or_array_layers: 1,
     };
-    let texture = app_view.device.create_texture(&wgpu::TextureDescriptor {
+    check_texture(&app_view.device, format, texture_extent, usage)?;
+    check_buffer_layout(buffer, texture_extent, pixel_size, format)?;
+    let tex_desc = wgpu::TextureDescriptor {
         size: texture_extent,
         mip_level_count: 1,
         sample_count: 1,
         dimension: wgpu::TextureDimension::D2,
         format,
         usage,
-        label: None,
-    });
+        label,
+    };
+    let texture = app_view.device.create_texture(&tex_desc);
     let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
 
     // BufferCopyView 必须 >= TextureCopyView
@@ -172,8 +425,8 @@ pub fn from_buffer(
             buffer,
             layout: wgpu::ImageDataLayout {
                 offset: 0,
-                bytes_per_row: Some(NonZeroU32::new(pixel_size * width).unwrap()),
-                rows_per_image: Some(NonZeroU32::new(height).unwrap()),
+                bytes_per_row: NonZeroU32::new(pixel_size * width),
+                rows_per_image: NonZeroU32::new(height),
             },
         },
         wgpu::ImageCopyTexture {
@@ -190,16 +443,20 @@ pub fn from_buffer(
         tex_view: texture_view,
         view_dimension: wgpu::TextureViewDimension::D2,
         format,
+        allocation: MemoryAllocation::texture(&tex_desc),
     };
 
-    (any_tex, default_sampler(&app_view.device))
+    Ok((any_tex, default_sampler(&app_view.device)))
 }
 
-fn load_from_path(
-    path: PathBuf,
+// 未指定 format 时由图片的颜色类型决定纹理格式
+fn image_texels(
+    device: &wgpu::Device,
+    img: image::DynamicImage,
+    path: Option<&Path>,
     set_to_grayscale: bool,
-) -> (Vec<u8>, wgpu::Extent3d, TextureFormat) {
-    let img = image::open(&path.as_path()).unwrap();
+    format: Option<TextureFormat>,
+) -> Result<(Vec<u8>, wgpu::Extent3d, TextureFormat), TextureError> {
     let (width, height) = img.dimensions();
     let texture_extent = wgpu::Extent3d {
         width,
@@ -207,25 +464,113 @@ fn load_from_path(
         depth_or_array_layers: 1,
     };
 
-    let (format, texels) = if set_to_grayscale {
-        // webgpu spec: R8 | R16 is not supported for storage use.
-        // (TextureFormat::R8Unorm, DynamicImage::ImageLuma16(img.into_luma8()).into_bytes())
-        (TextureFormat::R8Unorm, img.into_luma8().into_raw())
-    } else {
-        match img.color() {
-            image::ColorType::L8 => (TextureFormat::R8Unorm, img.into_bytes()),
-            image::ColorType::Rgb8 | image::ColorType::Rgba8 => {
-                (TextureFormat::Rgba8Unorm, img.into_rgba8().into_raw())
+    let format = match format {
+        Some(format) => format,
+        None => default_format(device.features(), img.color(), set_to_grayscale).ok_or_else(|| {
+            TextureError::UnsupportedColorType {
+                path: path.map(Path::to_path_buf),
+                color_type: img.color(),
             }
-            _ => panic!("unsupported color type"),
+        })?,
+    };
+    let required = format.describe().required_features;
+    if !device.features().contains(required) {
+        return Err(TextureError::MissingFeatures {
+            format,
+            features: required,
+        });
+    }
+
+    let texels = match format {
+        // webgpu spec: R8 | R16 is not supported for storage use.
+        TextureFormat::R8Unorm => img.into_luma8().into_raw(),
+        TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => img.into_rgba8().into_raw(),
+        TextureFormat::R16Unorm => bytemuck::cast_slice(&img.into_luma16().into_raw()).to_vec(),
+        TextureFormat::Rgba16Unorm => bytemuck::cast_slice(&img.into_rgba16().into_raw()).to_vec(),
+        TextureFormat::R16Float => {
+            let texels: Vec<u16> = img
+                .into_luma16()
+                .into_raw()
+                .into_iter()
+                .map(|v| f32_to_f16(v as f32 / u16::MAX as f32))
+                .collect();
+            bytemuck::cast_slice(&texels).to_vec()
         }
+        TextureFormat::Rgba16Float => {
+            let texels: Vec<u16> =
+                img.into_rgba32f().into_raw().into_iter().map(f32_to_f16).collect();
+            bytemuck::cast_slice(&texels).to_vec()
+        }
+        TextureFormat::R32Float => {
+            // 与 image 转为灰度图时使用相同的系数
+            let texels: Vec<f32> = img
+                .into_rgba32f()
+                .into_raw()
+                .chunks(4)
+                .map(|p| 0.2126 * p[0] + 0.7152 * p[1] + 0.0722 * p[2])
+                .collect();
+            bytemuck::cast_slice(&texels).to_vec()
+        }
+        TextureFormat::Rgba32Float => bytemuck::cast_slice(&img.into_rgba32f().into_raw()).to_vec(),
+        _ => return Err(TextureError::UnsupportedFormat { format }),
+    };
+
+    Ok((texels, texture_extent, format))
+}
+
+// 16 位整数格式优先使用 Unorm，device 没有开启 TEXTURE_FORMAT_16BIT_NORM 时使用 Float
+// 32 位浮点格式不能线性过滤，节点绑定时会使用 NonFiltering 采样器
+fn default_format(
+    features: wgpu::Features,
+    color_type: image::ColorType,
+    set_to_grayscale: bool,
+) -> Option<TextureFormat> {
+    let norm16 = features.contains(wgpu::Features::TEXTURE_FORMAT_16BIT_NORM);
+    let format = match color_type {
+        image::ColorType::L16 | image::ColorType::La16 if norm16 => TextureFormat::R16Unorm,
+        image::ColorType::L16 | image::ColorType::La16 => TextureFormat::R16Float,
+        _ if set_to_grayscale => TextureFormat::R8Unorm,
+        image::ColorType::L8 => TextureFormat::R8Unorm,
+        image::ColorType::Rgb8 | image::ColorType::Rgba8 => TextureFormat::Rgba8Unorm,
+        image::ColorType::Rgb16 | image::ColorType::Rgba16 if norm16 => TextureFormat::Rgba16Unorm,
+        image::ColorType::Rgb16 | image::ColorType::Rgba16 => TextureFormat::Rgba16Float,
+        image::ColorType::Rgb32F | image::ColorType::Rgba32F => TextureFormat::Rgba32Float,
+        _ => return None,
     };
+    Some(format)
+}
 
-    (texels, texture_extent, format)
+// 转换为半精度浮点数的位表示，舍入到最近值
+fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x007f_ffff;
+    if exponent == 0xff {
+        // inf 与 NaN
+        return sign | 0x7c00 | if mantissa != 0 { 0x0200 } else { 0 };
+    }
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if exponent <= 0 {
+        if exponent < -10 {
+            return sign;
+        }
+        // 非规格化数
+        let mantissa = mantissa | 0x0080_0000;
+        let shift = (14 - exponent) as u32;
+        let round = (mantissa >> (shift - 1)) & 1;
+        return sign | ((mantissa >> shift) + round) as u16;
+    }
+    let half = ((exponent as u32) << 10) | (mantissa >> 13);
+    let round = (mantissa >> 12) & 1;
+    sign | (half + round) as u16
 }
 
-fn load_by_luma(path: PathBuf) -> (Vec<u8>, wgpu::Extent3d) {
-    let img = image::open(&path.as_path()).unwrap();
+fn load_by_luma(path: PathBuf) -> Result<(Vec<u8>, wgpu::Extent3d), TextureError> {
+    let img = open_image(&path)?;
     let (width, height) = img.dimensions();
     let texture_extent = wgpu::Extent3d {
         width,
@@ -233,7 +578,135 @@ fn load_by_luma(path: PathBuf) -> (Vec<u8>, wgpu::Extent3d) {
         depth_or_array_layers: 1,
     };
 
-    (img.to_luma8().into_raw(), texture_extent)
+    Ok((img.to_luma8().into_raw(), texture_extent))
+}
+
+fn open_image(path: &Path) -> Result<image::DynamicImage, TextureError> {
+    #[cfg(feature = "hdr")]
+    let result = if image::ImageFormat::from_path(path).ok() == Some(image::ImageFormat::Hdr) {
+        std::fs::File::open(path)
+            .map_err(image::ImageError::IoError)
+            .and_then(|file| decode_hdr(std::io::BufReader::new(file)))
+    } else {
+        image::open(path)
+    };
+    #[cfg(not(feature = "hdr"))]
+    let result = image::open(path);
+    result.map_err(|e| match e {
+        image::ImageError::IoError(e) => TextureError::Io {
+            path: path.to_path_buf(),
+            reason: e.to_string(),
+        },
+        e => TextureError::Decode {
+            path: Some(path.to_path_buf()),
+            reason: decode_error_reason(e),
+        },
+    })
+}
+
+fn decode_bytes(bytes: &[u8]) -> image::ImageResult<image::DynamicImage> {
+    #[cfg(feature = "hdr")]
+    if image::guess_format(bytes).ok() == Some(image::ImageFormat::Hdr) {
+        return decode_hdr(bytes);
+    }
+    image::load_from_memory(bytes)
+}
+
+// image 的通用解码会把 .hdr 色调映射为 Rgb8，这里直接读取浮点数据
+#[cfg(feature = "hdr")]
+fn decode_hdr(reader: impl std::io::BufRead) -> image::ImageResult<image::DynamicImage> {
+    let decoder = image::codecs::hdr::HdrDecoder::new(reader)?;
+    let meta = decoder.metadata();
+    let raw: Vec<f32> = decoder.read_image_hdr()?.iter().flat_map(|pixel| pixel.0).collect();
+    let buffer = image::Rgb32FImage::from_raw(meta.width, meta.height, raw).ok_or_else(|| {
+        image::ImageError::Parameter(image::error::ParameterError::from_kind(
+            image::error::ParameterErrorKind::DimensionMismatch,
+        ))
+    })?;
+    Ok(image::DynamicImage::ImageRgb32F(buffer))
+}
+
+// 对应的解码器没有编译进来时，提示需要开启的 cargo feature
+fn decode_error_reason(e: image::ImageError) -> String {
+    if let image::ImageError::Unsupported(unsupported) = &e {
+        if let image::error::ImageFormatHint::Exact(format) = unsupported.format_hint() {
+            let feature = match format {
+                image::ImageFormat::Jpeg => Some("jpeg"),
+                image::ImageFormat::WebP => Some("webp"),
+                image::ImageFormat::Hdr => Some("hdr"),
+                image::ImageFormat::OpenExr => Some("exr"),
+                _ => None,
+            };
+            if let Some(feature) = feature {
+                return format!("{e} (enable the `{feature}` feature of idroid)");
+            }
+        }
+    }
+    e.to_string()
+}
+
+fn check_dimensions(device: &wgpu::Device, extent: Extent3d) -> Result<(), TextureError> {
+    let max = device.limits().max_texture_dimension_2d;
+    if extent.width == 0 || extent.height == 0 || extent.width > max || extent.height > max {
+        return Err(TextureError::InvalidDimensions {
+            width: extent.width,
+            height: extent.height,
+            max,
+        });
+    }
+    Ok(())
+}
+
+// 检查尺寸，以及格式是否支持 usage
+// buffer 到纹理的拷贝要求每行字节数是 256 的倍数，且 buffer 足够容纳所有行
+fn check_buffer_layout(
+    buffer: &wgpu::Buffer,
+    extent: Extent3d,
+    pixel_size: u32,
+    format: TextureFormat,
+) -> Result<(), TextureError> {
+    let format_pixel_size = single_pixel_bytes(format);
+    if pixel_size != format_pixel_size {
+        return Err(TextureError::InvalidBufferLayout {
+            reason: format!(
+                "pixel size {pixel_size} does not match the {format_pixel_size} bytes per pixel of {format:?}"
+            ),
+        });
+    }
+    let bytes_per_row = pixel_size * extent.width;
+    if bytes_per_row % wgpu::COPY_BYTES_PER_ROW_ALIGNMENT != 0 {
+        return Err(TextureError::InvalidBufferLayout {
+            reason: format!(
+                "bytes per row {bytes_per_row} is not a multiple of {}",
+                wgpu::COPY_BYTES_PER_ROW_ALIGNMENT
+            ),
+        });
+    }
+    let required = bytes_per_row as u64 * extent.height as u64;
+    if buffer.size() < required {
+        return Err(TextureError::InvalidBufferLayout {
+            reason: format!("buffer of {} bytes is smaller than the {required} bytes required", buffer.size()),
+        });
+    }
+    Ok(())
+}
+
+fn check_texture(
+    device: &wgpu::Device,
+    format: TextureFormat,
+    extent: Extent3d,
+    usage: wgpu::TextureUsages,
+) -> Result<(), TextureError> {
+    check_dimensions(device, extent)?;
+    // 开启了 TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES 时，实际支持的 usage 由 adapter 决定
+    let adapter_specific = device
+        .features()
+        .contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES);
+    let allowed = format.describe().guaranteed_format_features.allowed_usages;
+    if !adapter_specific && !allowed.contains(usage) {
+        return Err(TextureError::IncompatibleUsage { format, usage });
+    }
+    Ok(())
 }
 
 pub fn empty(
@@ -262,7 +735,7 @@ pub fn empty(
     } else {
         (wgpu::TextureDimension::D2, extent.depth_or_array_layers)
     };
-    let texture = device.create_texture(&wgpu::TextureDescriptor {
+    let tex_desc = wgpu::TextureDescriptor {
         size: extent,
         mip_level_count: 1,
         sample_count: 1,
@@ -270,7 +743,8 @@ pub fn empty(
         format,
         usage,
         label,
-    });
+    };
+    let texture = device.create_texture(&tex_desc);
     let mut view_label: String = String::from("view");
     if let Some(lb) = label {
         view_label = lb.to_string() + "_" + &view_label;
@@ -294,6 +768,7 @@ pub fn empty(
         tex_view: texture_view,
         view_dimension,
         format,
+        allocation: MemoryAllocation::texture(&tex_desc),
     }
 }
 
@@ -390,34 +865,133 @@ pub fn bilinear_sampler(device: &wgpu::Device) -> Sampler {
     })
 }
 
+// 每个像素的字节数，与纹理格式保持一致
 fn single_pixel_bytes(format: TextureFormat) -> u32 {
-    match format {
-        TextureFormat::R8Sint
-        | TextureFormat::R8Snorm
-        | TextureFormat::R8Uint
-        | TextureFormat::R8Unorm => 1,
-        TextureFormat::R16Float
-        | TextureFormat::R16Sint
-        | TextureFormat::R16Snorm
-        | TextureFormat::R16Uint
-        | TextureFormat::R16Unorm
-        | TextureFormat::Rg8Sint
-        | TextureFormat::Rg8Snorm
-        | TextureFormat::Rg8Uint
-        | TextureFormat::Rg8Unorm => 2,
-        TextureFormat::Rgba8Sint
-        | TextureFormat::Rgba8Uint
-        | TextureFormat::Bgra8Unorm
-        | TextureFormat::Bgra8UnormSrgb
-        | TextureFormat::Rgba8Snorm
-        | TextureFormat::Rgba8Unorm
-        | TextureFormat::Rgba8UnormSrgb => 4,
-        TextureFormat::Rgba16Float
-        | TextureFormat::Rgba16Sint
-        | TextureFormat::Rgba16Snorm
-        | TextureFormat::Rgba16Uint
-        | TextureFormat::Rgba16Unorm => 8,
-        TextureFormat::Rgba32Float | TextureFormat::Rgba32Sint | TextureFormat::Rgba32Uint => 16,
-        _ => 0,
+    format.describe().block_size as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_f32_to_f16() {
+        let cases = [
+            (0.0, 0x0000),
+            (-0.0, 0x8000),
+            (1.0, 0x3c00),
+            (-2.0, 0xc000),
+            (0.5, 0x3800),
+            (1.0 / 3.0, 0x3555),
+            (1.0 + 1.0 / 1024.0, 0x3c01),
+            (65504.0, 0x7bff),
+            // 最小的规格化数与非规格化数
+            (6.103_515_6e-5, 0x0400),
+            (5.960_464_5e-8, 0x0001),
+            (1.0e-8, 0x0000),
+            (1.0e6, 0x7c00),
+            (f32::INFINITY, 0x7c00),
+            (f32::NEG_INFINITY, 0xfc00),
+        ];
+        for (value, half) in cases {
+            assert_eq!(f32_to_f16(value), half, "{value}");
+        }
+        assert_eq!(f32_to_f16(f32::NAN) & 0x7c00, 0x7c00);
+        assert_ne!(f32_to_f16(f32::NAN) & 0x03ff, 0);
+    }
+
+    #[test]
+    fn picks_default_formats() {
+        let none = wgpu::Features::empty();
+        let norm16 = wgpu::Features::TEXTURE_FORMAT_16BIT_NORM;
+        let format = |features, color_type| default_format(features, color_type, false);
+        assert_eq!(format(none, image::ColorType::Rgba8), Some(TextureFormat::Rgba8Unorm));
+        assert_eq!(format(none, image::ColorType::L16), Some(TextureFormat::R16Float));
+        assert_eq!(format(norm16, image::ColorType::L16), Some(TextureFormat::R16Unorm));
+        assert_eq!(format(none, image::ColorType::Rgb16), Some(TextureFormat::Rgba16Float));
+        assert_eq!(format(norm16, image::ColorType::Rgba16), Some(TextureFormat::Rgba16Unorm));
+        assert_eq!(format(none, image::ColorType::Rgb32F), Some(TextureFormat::Rgba32Float));
+        assert_eq!(default_format(none, image::ColorType::Rgb8, true), Some(TextureFormat::R8Unorm));
+    }
+
+    #[cfg(feature = "hdr")]
+    fn encode_hdr(width: usize, height: usize) -> Vec<u8> {
+        let mut bytes = vec![];
+        let pixels = vec![image::Rgb([0.5_f32, 1.0, 2.0]); width * height];
+        image::codecs::hdr::HdrEncoder::new(&mut bytes).encode(&pixels, width, height).unwrap();
+        bytes
+    }
+
+    #[cfg(feature = "hdr")]
+    #[test]
+    fn decodes_hdr_as_float() {
+        let img = decode_bytes(&encode_hdr(2, 2)).unwrap();
+        assert_eq!(img.color(), image::ColorType::Rgb32F);
+        assert_eq!(img.dimensions(), (2, 2));
+        assert_eq!(img.to_rgb32f().get_pixel(1, 1).0, [0.5, 1.0, 2.0]);
+        assert_eq!(default_format(wgpu::Features::empty(), img.color(), false), Some(TextureFormat::Rgba32Float));
+    }
+
+    #[cfg(feature = "hdr")]
+    #[test]
+    #[ignore = "requires a GPU adapter"]
+    fn hdr_texture_binds_to_view_node() {
+        let (device, queue) = crate::test_util::headless_device();
+        let img = decode_bytes(&encode_hdr(2, 2)).unwrap();
+
+        let (texture, _) = create_from_image(
+            img,
+            None,
+            None,
+            &device,
+            &queue,
+            wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            false,
+            None,
+        )
+        .unwrap();
+        assert_eq!(texture.format, TextureFormat::Rgba32Float);
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(
+                r#"
+struct MVPUniform { mvp: mat4x4<f32> };
+@group(0) @binding(0) var<uniform> mvp_mat: MVPUniform;
+@group(0) @binding(1) var tex: texture_2d<f32>;
+@group(0) @binding(2) var tex_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@location(0) pos: vec3<f32>, @location(1) uv: vec2<f32>) -> VertexOutput {
+    var out: VertexOutput;
+    out.position = mvp_mat.mvp * vec4<f32>(pos, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(tex, tex_sampler, in.uv);
+}
+"#
+                .into(),
+            ),
+        });
+        // 采样器以 Filtering 方式绑定不可过滤的 Rgba32Float 纹理时，wgpu 的校验错误会 panic
+        let node = crate::node::ViewNodeBuilder::<crate::vertex::PosTex>::new(vec![(&texture, None)], &shader)
+            .with_color_format(TextureFormat::Rgba8Unorm)
+            .with_shader_stages(vec![
+                wgpu::ShaderStages::VERTEX,
+                wgpu::ShaderStages::FRAGMENT,
+                wgpu::ShaderStages::FRAGMENT,
+            ])
+            .build(&device);
+        device.poll(wgpu::Maintain::Wait);
+        drop(node);
     }
 }
@@ -0,0 +1,210 @@
+//! GPU 资源的显存统计
+//!
+//! 默认不统计。调用 [`enable_tracking`] 后，`BufferObj::create_*` 与 `load_texture::empty` / `from_path` /
+//! `from_buffer` 等创建的资源会按 label 分组记录由尺寸、格式及 mip 层级算出的字节数，资源 drop 时扣除。
+//! 由图片文件加载的纹理以其路径作为 label
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// 未设置 label 的资源归入此分组
+pub const UNLABELED: &str = "unlabeled";
+
+type BudgetHook = Arc<dyn Fn(&MemoryReport) + Send + Sync>;
+
+#[derive(Default)]
+struct Tracker {
+    labels: BTreeMap<String, LabelUsage>,
+    current: u64,
+    peak: u64,
+    budget: Option<(u64, BudgetHook)>,
+}
+
+// BTreeMap::new 在 rust 1.66 之前不是 const fn，首次使用时再创建
+static TRACKER: Mutex<Option<Tracker>> = Mutex::new(None);
+// 未开启统计时，创建资源不需要获取锁
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+fn with_tracker<R>(f: impl FnOnce(&mut Tracker) -> R) -> R {
+    f(TRACKER.lock().unwrap().get_or_insert_with(Tracker::default))
+}
+
+impl Tracker {
+    fn report(&self) -> MemoryReport {
+        MemoryReport { current_bytes: self.current, peak_bytes: self.peak, labels: self.labels.clone() }
+    }
+}
+
+/// 同一个 label 下的资源占用
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LabelUsage {
+    pub current_bytes: u64,
+    pub peak_bytes: u64,
+    /// 当前存活的资源个数
+    pub count: u32,
+}
+
+/// [`report`] 返回的统计快照
+#[derive(Clone, Debug, Default)]
+pub struct MemoryReport {
+    pub current_bytes: u64,
+    pub peak_bytes: u64,
+    pub labels: BTreeMap<String, LabelUsage>,
+}
+
+impl std::fmt::Display for MemoryReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "GPU memory: {} bytes (peak {} bytes)", self.current_bytes, self.peak_bytes)?;
+        for (label, usage) in self.labels.iter() {
+            writeln!(
+                f,
+                "  {label}: {} bytes in {} resources (peak {} bytes)",
+                usage.current_bytes, usage.count, usage.peak_bytes
+            )?;
+        }
+        Ok(())
+    }
+}
+
+pub fn enable_tracking() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// 停止统计新创建的资源，已统计的资源 drop 时仍会扣除
+pub fn disable_tracking() {
+    ENABLED.store(false, Ordering::Relaxed);
+}
+
+pub fn is_tracking() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// 当前统计的快照，按 label 分组
+pub fn report() -> MemoryReport {
+    with_tracker(|tracker| tracker.report())
+}
+
+/// 设置显存预算，总占用从预算以内超出预算时调用 `hook`
+pub fn set_budget(bytes: u64, hook: impl Fn(&MemoryReport) + Send + Sync + 'static) {
+    with_tracker(|tracker| tracker.budget = Some((bytes, Arc::new(hook))));
+}
+
+pub fn clear_budget() {
+    with_tracker(|tracker| tracker.budget = None);
+}
+
+/// 纹理占用的字节数，包含所有 mip 层级及数组层
+pub fn texture_size(
+    format: wgpu::TextureFormat, extent: wgpu::Extent3d, dimension: wgpu::TextureDimension, mip_level_count: u32,
+    sample_count: u32,
+) -> u64 {
+    let info = format.describe();
+    let (block_width, block_height) = (info.block_dimensions.0 as u32, info.block_dimensions.1 as u32);
+    let mut bytes = 0;
+    for level in 0..mip_level_count.max(1) {
+        let width = (extent.width >> level).max(1);
+        let height = (extent.height >> level).max(1);
+        // 3D 纹理的深度随 mip 层级减半，2D 纹理数组的层数不变
+        let depth = if dimension == wgpu::TextureDimension::D3 {
+            (extent.depth_or_array_layers >> level).max(1)
+        } else {
+            extent.depth_or_array_layers
+        };
+        let blocks =
+            ((width + block_width - 1) / block_width) as u64 * ((height + block_height - 1) / block_height) as u64;
+        bytes += blocks * info.block_size as u64 * depth as u64;
+    }
+    bytes * sample_count.max(1) as u64
+}
+
+/// 资源对应的统计记录，drop 时从统计中扣除
+///
+/// 未开启统计时创建的记录不占用统计；在外部直接构造 `BufferObj` / `AnyTexture` 时可以使用 `Default`
+#[derive(Debug, Default)]
+pub struct MemoryAllocation {
+    tracked: Option<(String, u64)>,
+}
+
+impl MemoryAllocation {
+    pub fn new(label: Option<&str>, bytes: u64) -> Self {
+        if !is_tracking() {
+            return MemoryAllocation::default();
+        }
+        let label = label.unwrap_or(UNLABELED).to_string();
+        let crossed = with_tracker(|tracker| {
+            let usage = tracker.labels.entry(label.clone()).or_default();
+            usage.current_bytes += bytes;
+            usage.peak_bytes = usage.peak_bytes.max(usage.current_bytes);
+            usage.count += 1;
+
+            let before = tracker.current;
+            tracker.current += bytes;
+            tracker.peak = tracker.peak.max(tracker.current);
+            match &tracker.budget {
+                Some((budget, hook)) if before <= *budget && tracker.current > *budget => {
+                    Some((hook.clone(), tracker.report()))
+                }
+                _ => None,
+            }
+        });
+        // 释放锁后再调用，hook 中可以再次查询统计
+        if let Some((hook, report)) = crossed {
+            hook(&report);
+        }
+        MemoryAllocation { tracked: Some((label, bytes)) }
+    }
+
+    pub fn buffer(label: Option<&str>, size: wgpu::BufferAddress) -> Self {
+        Self::new(label, size)
+    }
+
+    pub fn texture(desc: &wgpu::TextureDescriptor) -> Self {
+        let bytes = texture_size(desc.format, desc.size, desc.dimension, desc.mip_level_count, desc.sample_count);
+        Self::new(desc.label, bytes)
+    }
+
+    /// 统计的字节数，未统计时为 0
+    pub fn bytes(&self) -> u64 {
+        self.tracked.as_ref().map_or(0, |(_, bytes)| *bytes)
+    }
+}
+
+impl Drop for MemoryAllocation {
+    fn drop(&mut self) {
+        if let Some((label, bytes)) = self.tracked.take() {
+            with_tracker(|tracker| {
+                tracker.current -= bytes;
+                if let Some(usage) = tracker.labels.get_mut(&label) {
+                    usage.current_bytes -= bytes;
+                    usage.count -= 1;
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extent(width: u32, height: u32, depth_or_array_layers: u32) -> wgpu::Extent3d {
+        wgpu::Extent3d { width, height, depth_or_array_layers }
+    }
+
+    #[test]
+    fn computes_texture_size() {
+        use wgpu::{TextureDimension, TextureFormat};
+        let d2 = TextureDimension::D2;
+        assert_eq!(texture_size(TextureFormat::Rgba8Unorm, extent(256, 128, 1), d2, 1, 1), 256 * 128 * 4);
+        assert_eq!(texture_size(TextureFormat::Rgba32Float, extent(3, 5, 1), d2, 1, 4), 3 * 5 * 16 * 4);
+        // mip 层级逐级减半，最小为 1
+        assert_eq!(texture_size(TextureFormat::R8Unorm, extent(4, 2, 1), d2, 3, 1), 8 + 2 + 1);
+        // 2D 纹理数组的层数不随 mip 层级变化，3D 纹理的深度减半
+        assert_eq!(texture_size(TextureFormat::R8Unorm, extent(4, 4, 6), d2, 2, 1), (16 + 4) * 6);
+        assert_eq!(texture_size(TextureFormat::R8Unorm, extent(4, 4, 4), TextureDimension::D3, 2, 1), 64 + 8);
+        // 压缩格式按 4x4 的块计算，不足一个块的按一个块计算
+        assert_eq!(texture_size(TextureFormat::Bc1RgbaUnorm, extent(6, 6, 1), d2, 1, 1), 4 * 8);
+        assert_eq!(texture_size(TextureFormat::Bc7RgbaUnorm, extent(8, 8, 1), d2, 2, 1), 4 * 16 + 16);
+    }
+}
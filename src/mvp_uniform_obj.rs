@@ -14,6 +14,8 @@ pub struct MVPUniform2 {
     pub p_matrix: [[f32; 4]; 4],
     pub mv_matrix: [[f32; 4]; 4],
 }
+crate::impl_uniform_layout!(MVPUniform { mvp_matrix });
+crate::impl_uniform_layout!(MVPUniform2 { p_matrix, mv_matrix });
 
 impl MVPUniform {
     pub fn zero() -> Self {
@@ -1,3 +1,4 @@
+use crate::shader::{BindingReflection, ShaderError, ShaderReflection};
 use crate::{buffer::BufferObj, AnyTexture};
 use std::vec::Vec;
 use wgpu::{StorageTextureAccess, TextureFormat};
@@ -6,6 +7,26 @@ use wgpu::{StorageTextureAccess, TextureFormat};
 pub struct BindingGroupSetting {
     pub bind_group_layout: wgpu::BindGroupLayout,
     pub bind_group: wgpu::BindGroup,
+    group: u32,
+    // 按 binding 序号排列，记录创建 bind group 时资源的 generation，非 GrowableBuffer 的资源为 0
+    bindings: Vec<(u32, u64)>,
+    // 节点自己创建的资源，如默认的 mvp uniform 及采样器，重建 bind group 时不需要调用方提供
+    owned: Vec<(u32, OwnedResource)>,
+}
+
+/// 由节点创建并持有的绑定资源
+pub enum OwnedResource {
+    Buffer(BufferObj),
+    Sampler(wgpu::Sampler),
+}
+
+impl OwnedResource {
+    fn as_resource_ref(&self) -> BindingResourceRef<'_> {
+        match self {
+            OwnedResource::Buffer(buffer_obj) => BindingResourceRef::Buffer(buffer_obj),
+            OwnedResource::Sampler(sampler) => BindingResourceRef::Sampler(sampler),
+        }
+    }
 }
 
 #[allow(dead_code)]
@@ -21,6 +42,9 @@ impl BindingGroupSetting {
         // 关于 min_binding_size
         // https://gpuweb.github.io/gpuweb/#dom-gpubindgrouplayoutentry-minbufferbindingsize
         let mut b_index = 0_u32;
+        let binding_count = uniforms.len() + storage_buffers.len() + textures.len() + samplers.len();
+        // 不能从 &BufferObj 得知 generation，GrowableBuffer 未扩容时的 generation 即为 0
+        let bindings = (0..binding_count as u32).map(|binding| (binding, 0)).collect();
         for i in 0..uniforms.len() {
             let buffer_obj = uniforms[i];
             layouts.push(wgpu::BindGroupLayoutEntry {
@@ -80,11 +104,15 @@ impl BindingGroupSetting {
             b_index += 1;
         }
 
+        let sampler_type = sampler_binding_type(
+            textures.iter().map(|(any_tex, access)| (*any_tex, access.is_some())),
+            wgpu::SamplerBindingType::Filtering,
+        );
         for i in 0..samplers.len() {
             layouts.push(wgpu::BindGroupLayoutEntry {
                 binding: b_index,
                 visibility: visibilitys[b_index as usize],
-                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                ty: wgpu::BindingType::Sampler(sampler_type),
                 count: None,
             });
             entries
@@ -101,14 +129,444 @@ impl BindingGroupSetting {
             label: None,
         });
 
-        BindingGroupSetting { bind_group_layout, bind_group }
+        BindingGroupSetting { bind_group_layout, bind_group, group: 0, bindings, owned: vec![] }
     }
+
+    /// 由着色器反射信息生成 `@group(group)` 的布局：binding 序号、visibility、storage 的读写属性、
+    /// 纹理的采样类型及 view dimension 都取自着色器中的声明，资源按名称或序号对应。
+    ///
+    /// 没有被任何入口函数使用的声明可以不提供资源，提供的资源与声明不一致时返回错误
+    pub fn from_reflection(
+        device: &wgpu::Device, reflection: &ShaderReflection, group: u32,
+        resources: &[(BindingSlot, BindingResourceRef)],
+    ) -> Result<Self, ShaderError> {
+        let resolved = resolve_slots(reflection, group, resources)?;
+
+        let bindings = resolved.iter().map(|(b, resource)| (b.binding, resource.generation())).collect();
+        let sampled_textures: Vec<(&AnyTexture, bool)> = resolved
+            .iter()
+            .filter_map(|(b, resource)| match resource {
+                BindingResourceRef::Texture(any_tex) => {
+                    Some((*any_tex, matches!(b.ty, wgpu::BindingType::StorageTexture { .. })))
+                }
+                _ => None,
+            })
+            .collect();
+        let mut layouts: Vec<wgpu::BindGroupLayoutEntry> = vec![];
+        let mut entries: Vec<wgpu::BindGroupEntry> = vec![];
+        for (b, resource) in resolved {
+            if b.count.is_some() {
+                return Err(reflection.binding_mismatch(b, "binding arrays are not supported".to_string()));
+            }
+            let ty = match (b.ty, resource) {
+                (
+                    wgpu::BindingType::Buffer { ty, min_binding_size, .. },
+                    BindingResourceRef::Buffer(buffer_obj) | BindingResourceRef::GrowableBuffer(buffer_obj, _),
+                ) => {
+                    if let Some(min_size) = min_binding_size {
+                        if buffer_obj.size < min_size.get() {
+                            return Err(reflection.binding_mismatch(
+                                b,
+                                format!("buffer size {} is less than the declared size {}", buffer_obj.size, min_size),
+                            ));
+                        }
+                    }
+                    let usage = match ty {
+                        wgpu::BufferBindingType::Uniform => wgpu::BufferUsages::UNIFORM,
+                        wgpu::BufferBindingType::Storage { .. } => wgpu::BufferUsages::STORAGE,
+                    };
+                    if !buffer_obj.buffer.usage().contains(usage) {
+                        return Err(reflection.binding_mismatch(b, format!("buffer was not created with {:?}", usage)));
+                    }
+                    wgpu::BindingType::Buffer {
+                        ty,
+                        has_dynamic_offset: buffer_obj.has_dynamic_offset,
+                        min_binding_size: wgpu::BufferSize::new(0),
+                    }
+                }
+                (
+                    wgpu::BindingType::Texture { sample_type, view_dimension, multisampled },
+                    BindingResourceRef::Texture(any_tex),
+                ) => {
+                    check_view_dimension(reflection, b, view_dimension, any_tex)?;
+                    let sample_type = match (sample_type, any_tex.format.describe().sample_type) {
+                        (wgpu::TextureSampleType::Float { .. }, wgpu::TextureSampleType::Float { .. }) => {
+                            wgpu::TextureSampleType::Float { filterable: texture_sample_filterable(any_tex.format) }
+                        }
+                        (declared, given) if declared == given => declared,
+                        (declared, given) => {
+                            return Err(reflection.binding_mismatch(
+                                b,
+                                format!(
+                                    "texture format {:?} samples as {:?}, but the shader expects {:?}",
+                                    any_tex.format, given, declared
+                                ),
+                            ))
+                        }
+                    };
+                    wgpu::BindingType::Texture { sample_type, view_dimension, multisampled }
+                }
+                (
+                    wgpu::BindingType::StorageTexture { access, format, view_dimension },
+                    BindingResourceRef::Texture(any_tex),
+                ) => {
+                    check_view_dimension(reflection, b, view_dimension, any_tex)?;
+                    if format != any_tex.format {
+                        return Err(reflection.binding_mismatch(
+                            b,
+                            format!("texture format is {:?}, but the shader expects {:?}", any_tex.format, format),
+                        ));
+                    }
+                    wgpu::BindingType::StorageTexture { access, format, view_dimension }
+                }
+                (wgpu::BindingType::Sampler(ty), BindingResourceRef::Sampler(_)) => {
+                    wgpu::BindingType::Sampler(sampler_binding_type(sampled_textures.iter().copied(), ty))
+                }
+                (declared, resource) => {
+                    return Err(reflection.binding_mismatch(
+                        b,
+                        format!(
+                            "the shader declares {}, but {} was provided",
+                            binding_kind(&declared),
+                            resource.kind()
+                        ),
+                    ))
+                }
+            };
+            layouts.push(wgpu::BindGroupLayoutEntry { binding: b.binding, visibility: b.visibility, ty, count: None });
+            entries.push(wgpu::BindGroupEntry { binding: b.binding, resource: resource.binding_resource() });
+        }
+
+        let bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor { entries: &layouts, label: None });
+        let bind_group: wgpu::BindGroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &entries,
+            label: None,
+        });
+
+        Ok(BindingGroupSetting { bind_group_layout, bind_group, group, bindings, owned: vec![] })
+    }
+
+    /// 由节点持有 `binding` 上的资源，`refresh_bind_group` 时自动使用
+    pub fn own_resource(&mut self, binding: u32, resource: OwnedResource) {
+        self.owned.retain(|(b, _)| *b != binding);
+        self.owned.push((binding, resource));
+    }
+
+    /// `resources` 中的 GrowableBuffer 扩容后（generation 变化），用新的 buffer 重建 bind group，
+    /// 布局保持不变，使用此布局的 pipeline 不需要重建。重建了 bind group 时返回 true
+    ///
+    /// `resources` 为 `(binding 序号, 资源)`，顺序不限。wgpu 的 bind group 不能只替换其中一部分，
+    /// 而调用方传入的资源在创建后没有被持有，所以需要提供创建时由调用方传入的全部资源；
+    /// 由节点持有的资源（见 [`BindingGroupSetting::own_resource`]）不需要也不能再传入
+    pub fn refresh_bind_group(
+        &mut self, device: &wgpu::Device, resources: &[(u32, BindingResourceRef)],
+    ) -> Result<bool, ShaderError> {
+        for (index, (binding, _)) in resources.iter().enumerate() {
+            if !self.bindings.iter().any(|(b, _)| b == binding) {
+                return Err(ShaderError::UnknownBinding {
+                    group: self.group,
+                    slot: BindingSlot::Index(*binding).to_string(),
+                });
+            }
+            if self.owned.iter().any(|(b, _)| b == binding) {
+                return Err(self.mismatch(*binding, "the resource is owned by the node"));
+            }
+            if resources[..index].iter().any(|(b, _)| b == binding) {
+                return Err(self.mismatch(*binding, "more than one resource was provided"));
+            }
+        }
+
+        let mut changed = false;
+        let mut entries: Vec<wgpu::BindGroupEntry> = vec![];
+        for (binding, generation) in self.bindings.iter() {
+            let resource = if let Some((_, owned)) = self.owned.iter().find(|(b, _)| b == binding) {
+                owned.as_resource_ref()
+            } else {
+                match resources.iter().find(|(b, _)| b == binding) {
+                    Some((_, resource)) => {
+                        changed |= resource.generation() != *generation;
+                        *resource
+                    }
+                    None => return Err(self.mismatch(*binding, "no resource was provided")),
+                }
+            };
+            entries.push(wgpu::BindGroupEntry { binding: *binding, resource: resource.binding_resource() });
+        }
+        if !changed {
+            return Ok(false);
+        }
+        self.bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.bind_group_layout,
+            entries: &entries,
+            label: None,
+        });
+        for (binding, generation) in self.bindings.iter_mut() {
+            if let Some((_, resource)) = resources.iter().find(|(b, _)| b == binding) {
+                *generation = resource.generation();
+            }
+        }
+        Ok(true)
+    }
+
+    fn mismatch(&self, binding: u32, message: &str) -> ShaderError {
+        ShaderError::BindingMismatch {
+            group: self.group,
+            binding,
+            message: message.to_string(),
+            location: None,
+            include_chain: vec![],
+        }
+    }
+}
+
+// 将按名称或序号给出的资源对应到 `group` 中声明的绑定，按 binding 序号排序；
+// 有重复、未声明或缺少（被入口使用却没有提供）的资源时返回错误
+fn resolve_slots<'r, 'a, T>(
+    reflection: &'r ShaderReflection, group: u32, resources: &'a [(BindingSlot, T)],
+) -> Result<Vec<(&'r BindingReflection, &'a T)>, ShaderError> {
+    let mut resolved: Vec<(&BindingReflection, &T)> = vec![];
+    for (slot, resource) in resources.iter() {
+        let declared = match slot {
+            BindingSlot::Name(name) => reflection.group_bindings(group).find(|b| b.name.as_deref() == Some(*name)),
+            BindingSlot::Index(index) => reflection.binding(group, *index),
+        };
+        match declared {
+            Some(b) if resolved.iter().any(|(r, _)| r.binding == b.binding) => {
+                return Err(reflection.binding_mismatch(b, "more than one resource was provided".to_string()))
+            }
+            Some(b) => resolved.push((b, resource)),
+            None => return Err(ShaderError::UnknownBinding { group, slot: slot.to_string() }),
+        }
+    }
+    for b in reflection.group_bindings(group) {
+        if !b.visibility.is_empty() && !resolved.iter().any(|(r, _)| r.binding == b.binding) {
+            return Err(reflection.binding_mismatch(b, "no resource was provided".to_string()));
+        }
+    }
+    resolved.sort_by_key(|(b, _)| b.binding);
+    Ok(resolved)
+}
+
+/// 资源对应着色器中的哪个绑定：变量名或 `@binding` 序号
+#[derive(Clone, Copy, Debug)]
+pub enum BindingSlot<'a> {
+    Name(&'a str),
+    Index(u32),
 }
 
+impl<'a> From<&'a str> for BindingSlot<'a> {
+    fn from(name: &'a str) -> Self {
+        BindingSlot::Name(name)
+    }
+}
+
+impl<'a> From<u32> for BindingSlot<'a> {
+    fn from(index: u32) -> Self {
+        BindingSlot::Index(index)
+    }
+}
+
+impl<'a> std::fmt::Display for BindingSlot<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BindingSlot::Name(name) => write!(f, "`{name}`"),
+            BindingSlot::Index(index) => write!(f, "@binding({index})"),
+        }
+    }
+}
+
+/// 按反射信息绑定的资源
+#[derive(Clone, Copy)]
+pub enum BindingResourceRef<'a> {
+    Buffer(&'a BufferObj),
+    /// GrowableBuffer 当前的 buffer 及其 generation
+    GrowableBuffer(&'a BufferObj, u64),
+    Texture(&'a AnyTexture),
+    Sampler(&'a wgpu::Sampler),
+}
+
+impl<'a> BindingResourceRef<'a> {
+    fn kind(&self) -> &'static str {
+        match self {
+            BindingResourceRef::Buffer(_) | BindingResourceRef::GrowableBuffer(..) => "a buffer",
+            BindingResourceRef::Texture(_) => "a texture",
+            BindingResourceRef::Sampler(_) => "a sampler",
+        }
+    }
+
+    fn generation(&self) -> u64 {
+        match self {
+            BindingResourceRef::GrowableBuffer(_, generation) => *generation,
+            _ => 0,
+        }
+    }
+
+    fn binding_resource(&self) -> wgpu::BindingResource<'a> {
+        match *self {
+            BindingResourceRef::Buffer(buffer_obj) | BindingResourceRef::GrowableBuffer(buffer_obj, _) => {
+                buffer_obj.buffer.as_entire_binding()
+            }
+            BindingResourceRef::Texture(any_tex) => wgpu::BindingResource::TextureView(&any_tex.tex_view),
+            BindingResourceRef::Sampler(sampler) => wgpu::BindingResource::Sampler(sampler),
+        }
+    }
+}
+
+impl<'a> From<&'a BufferObj> for BindingResourceRef<'a> {
+    fn from(buffer_obj: &'a BufferObj) -> Self {
+        BindingResourceRef::Buffer(buffer_obj)
+    }
+}
+
+impl<'a, T: bytemuck::Pod> From<&'a crate::TypedBuffer<T>> for BindingResourceRef<'a> {
+    fn from(buffer: &'a crate::TypedBuffer<T>) -> Self {
+        BindingResourceRef::Buffer(buffer.buffer_obj())
+    }
+}
+
+impl<'a, T: bytemuck::Pod> From<&'a crate::GrowableBuffer<T>> for BindingResourceRef<'a> {
+    fn from(buffer: &'a crate::GrowableBuffer<T>) -> Self {
+        BindingResourceRef::GrowableBuffer(buffer.buffer_obj(), buffer.generation())
+    }
+}
+
+impl<'a> From<&'a AnyTexture> for BindingResourceRef<'a> {
+    fn from(any_tex: &'a AnyTexture) -> Self {
+        BindingResourceRef::Texture(any_tex)
+    }
+}
+
+impl<'a> From<&'a wgpu::Sampler> for BindingResourceRef<'a> {
+    fn from(sampler: &'a wgpu::Sampler) -> Self {
+        BindingResourceRef::Sampler(sampler)
+    }
+}
+
+fn check_view_dimension(
+    reflection: &ShaderReflection, b: &BindingReflection, declared: wgpu::TextureViewDimension, any_tex: &AnyTexture,
+) -> Result<(), ShaderError> {
+    if declared == any_tex.view_dimension {
+        Ok(())
+    } else {
+        Err(reflection.binding_mismatch(
+            b,
+            format!("texture view dimension is {:?}, but the shader expects {:?}", any_tex.view_dimension, declared),
+        ))
+    }
+}
+
+fn binding_kind(ty: &wgpu::BindingType) -> &'static str {
+    match ty {
+        wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, .. } => "a uniform buffer",
+        wgpu::BindingType::Buffer { .. } => "a storage buffer",
+        wgpu::BindingType::Texture { .. } => "a texture",
+        wgpu::BindingType::StorageTexture { .. } => "a storage texture",
+        wgpu::BindingType::Sampler(_) => "a sampler",
+    }
+}
+
+// 如 R32Float, Rgba32Float 不能线性过滤，绑定时需声明为 `filterable: false`，并配合 NonFiltering 采样器
 fn texture_sample_filterable(format: TextureFormat) -> bool {
-    match format {
-        // on iOS: texture binding 1 expects sample type = Float { filterable: true }, but given a view with format = R32Float
-        TextureFormat::R32Float => false,
-        _ => true,
+    format.describe().guaranteed_format_features.flags.contains(wgpu::TextureFormatFeatureFlags::FILTERABLE)
+}
+
+// 与不可过滤的纹理一起使用的采样器只能是 NonFiltering
+fn sampler_binding_type<'a>(
+    mut textures: impl Iterator<Item = (&'a AnyTexture, bool)>, ty: wgpu::SamplerBindingType,
+) -> wgpu::SamplerBindingType {
+    let non_filterable = textures.any(|(any_tex, is_storage)| {
+        !is_storage
+            && matches!(any_tex.format.describe().sample_type, wgpu::TextureSampleType::Float { .. })
+            && !texture_sample_filterable(any_tex.format)
+    });
+    if non_filterable && ty == wgpu::SamplerBindingType::Filtering {
+        wgpu::SamplerBindingType::NonFiltering
+    } else {
+        ty
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shader::{EmbeddedAssetSource, ShaderPreprocessor};
+
+    const SHADER: &str = "struct Params { scale: vec4<f32> };\n\
+                          @group(0) @binding(0) var<uniform> params: Params;\n\
+                          @group(0) @binding(1) var<storage, read_write> data: array<f32>;\n\
+                          @group(0) @binding(2) var unused_tex: texture_2d<f32>;\n\
+                          @compute @workgroup_size(1)\nfn cs_main() { data[0] = params.scale.x; }\n";
+
+    fn reflection() -> ShaderReflection {
+        let shader = ShaderPreprocessor::from_source(EmbeddedAssetSource::new(&[("bindings.wgsl", SHADER)]))
+            .process("bindings")
+            .unwrap();
+        crate::shader::reflect(&shader).unwrap()
+    }
+
+    fn mismatch_message(error: ShaderError, expected_binding: u32) -> String {
+        match error {
+            ShaderError::BindingMismatch { group: 0, binding, message, .. } if binding == expected_binding => message,
+            other => panic!("expected a mismatch at @binding({expected_binding}), got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn resolves_slots_by_name_and_index() {
+        let reflection = reflection();
+        // 没有被入口使用的 unused_tex 可以不提供
+        let resources = [(BindingSlot::Name("data"), "data"), (BindingSlot::Index(0), "params")];
+        let resolved = resolve_slots(&reflection, 0, &resources).unwrap();
+        let resolved: Vec<(u32, &str)> = resolved.iter().map(|(b, r)| (b.binding, **r)).collect();
+        assert_eq!(resolved, [(0, "params"), (1, "data")]);
+    }
+
+    #[test]
+    fn reports_unknown_duplicate_and_missing_slots() {
+        let reflection = reflection();
+        for slot in [BindingSlot::Name("missing"), BindingSlot::Index(5)] {
+            let resources = [(BindingSlot::Index(0), ()), (BindingSlot::Index(1), ()), (slot, ())];
+            match resolve_slots(&reflection, 0, &resources) {
+                Err(ShaderError::UnknownBinding { group: 0, slot: name }) => assert_eq!(name, slot.to_string()),
+                other => panic!("expected an unknown binding, got {:?}", other.map(|r| r.len())),
+            }
+        }
+
+        let resources = [(BindingSlot::Name("params"), ()), (BindingSlot::Index(0), ()), (BindingSlot::Index(1), ())];
+        let message = mismatch_message(resolve_slots(&reflection, 0, &resources).unwrap_err(), 0);
+        assert!(message.contains("more than one"), "{message}");
+
+        let resources = [(BindingSlot::Name("params"), ())];
+        let message = mismatch_message(resolve_slots(&reflection, 0, &resources).unwrap_err(), 1);
+        assert!(message.contains("no resource"), "{message}");
+    }
+
+    #[test]
+    #[ignore = "requires a GPU adapter"]
+    fn reports_mismatched_resources() {
+        let (device, _queue) = crate::test_util::headless_device();
+        let reflection = reflection();
+        let params = BufferObj::create_uniform_buffer(&device, &[1.0_f32; 4], None);
+        let small_params = BufferObj::create_uniform_buffer(&device, &[1.0_f32; 2], None);
+        let data = BufferObj::create_empty_storage_buffer(&device, 16, false, None);
+        let sampler = crate::load_texture::default_sampler(&device);
+        let bind = |params: BindingResourceRef, data: BindingResourceRef| {
+            BindingGroupSetting::from_reflection(
+                &device,
+                &reflection,
+                0,
+                &[(BindingSlot::Name("params"), params), (BindingSlot::Name("data"), data)],
+            )
+        };
+
+        assert!(bind(BindingResourceRef::Buffer(&params), BindingResourceRef::Buffer(&data)).is_ok());
+        let error = bind(BindingResourceRef::Buffer(&small_params), BindingResourceRef::Buffer(&data)).err().unwrap();
+        assert!(mismatch_message(error, 0).contains("less than the declared size"));
+        let error = bind(BindingResourceRef::Buffer(&data), BindingResourceRef::Buffer(&data)).err().unwrap();
+        assert!(mismatch_message(error, 0).contains("UNIFORM"));
+        let error = bind(BindingResourceRef::Buffer(&params), BindingResourceRef::Sampler(&sampler)).err().unwrap();
+        assert_eq!(mismatch_message(error, 1), "the shader declares a storage buffer, but a sampler was provided");
     }
 }
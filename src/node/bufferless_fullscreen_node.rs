@@ -7,12 +7,76 @@ pub struct BufferlessFullscreenNode {
     pipeline: wgpu::RenderPipeline,
 }
 
+/// [`BufferlessFullscreenNode::from_descriptor`] 的参数，入口函数默认为 `vs_main` 与 `fs_main`
+pub struct BufferlessFullscreenDescriptor<'a> {
+    pub format: TextureFormat,
+    pub uniforms: Vec<&'a BufferObj>,
+    pub storage_buffers: Vec<&'a BufferObj>,
+    pub textures: Vec<&'a AnyTexture>,
+    pub samplers: Vec<&'a wgpu::Sampler>,
+    pub shader_module: &'a ShaderModule,
+    pub color_blend_state: Option<wgpu::BlendState>,
+    pub use_depth_stencil: bool,
+    pub vs_entry_point: &'a str,
+    pub fs_entry_point: &'a str,
+}
+
+impl<'a> BufferlessFullscreenDescriptor<'a> {
+    pub fn new(format: TextureFormat, shader_module: &'a ShaderModule) -> Self {
+        BufferlessFullscreenDescriptor {
+            format,
+            uniforms: vec![],
+            storage_buffers: vec![],
+            textures: vec![],
+            samplers: vec![],
+            shader_module,
+            color_blend_state: None,
+            use_depth_stencil: false,
+            vs_entry_point: "vs_main",
+            fs_entry_point: "fs_main",
+        }
+    }
+
+    pub fn with_entry_points(mut self, vs_entry_point: &'a str, fs_entry_point: &'a str) -> Self {
+        self.vs_entry_point = vs_entry_point;
+        self.fs_entry_point = fs_entry_point;
+        self
+    }
+}
+
 impl BufferlessFullscreenNode {
     pub fn new(
         device: &wgpu::Device, format: TextureFormat, uniforms: Vec<&BufferObj>, storage_buffers: Vec<&BufferObj>,
         textures: Vec<&crate::AnyTexture>, samplers: Vec<&wgpu::Sampler>, shader_module: &ShaderModule,
         color_blend_state: Option<wgpu::BlendState>, use_depth_stencil: bool,
     ) -> Self {
+        Self::from_descriptor(
+            device,
+            BufferlessFullscreenDescriptor {
+                uniforms,
+                storage_buffers,
+                textures,
+                samplers,
+                color_blend_state,
+                use_depth_stencil,
+                ..BufferlessFullscreenDescriptor::new(format, shader_module)
+            },
+        )
+    }
+
+    pub fn from_descriptor(device: &wgpu::Device, descriptor: BufferlessFullscreenDescriptor) -> Self {
+        let BufferlessFullscreenDescriptor {
+            format,
+            uniforms,
+            storage_buffers,
+            textures,
+            samplers,
+            shader_module,
+            color_blend_state,
+            use_depth_stencil,
+            vs_entry_point,
+            fs_entry_point,
+        } = descriptor;
         let pipeline_vertex_buffers = [];
         let blend_state =
             if color_blend_state.is_some() { color_blend_state } else { Some(crate::utils::default_blend()) };
@@ -21,12 +85,12 @@ impl BufferlessFullscreenNode {
             layout: None,
             vertex: wgpu::VertexState {
                 module: shader_module,
-                entry_point: "vs_main",
+                entry_point: vs_entry_point,
                 buffers: &pipeline_vertex_buffers,
             },
             fragment: Some(wgpu::FragmentState {
                 module: shader_module,
-                entry_point: "fs_main",
+                entry_point: fs_entry_point,
                 targets: &[Some(wgpu::ColorTargetState {
                     format,
                     blend: blend_state,
@@ -113,3 +177,36 @@ pub fn create_bind_group(
 
     device.create_bind_group(&wgpu::BindGroupDescriptor { layout: bind_group_layout, entries: &entries, label: None })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = "@group(0) @binding(0) var<uniform> color: vec4<f32>;\n\
+                          @vertex\nfn vs_fullscreen(@builtin(vertex_index) i: u32) -> @builtin(position) vec4<f32> {\n\
+                          let uv = vec2<f32>(f32((i << 1u) & 2u), f32(i & 2u));\n\
+                          return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);\n}\n\
+                          @fragment\nfn fs_solid() -> @location(0) vec4<f32> { return color; }\n";
+
+    #[test]
+    #[ignore = "requires a GPU adapter"]
+    fn uses_descriptor_entry_points() {
+        let (device, _queue) = crate::test_util::headless_device();
+        let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        let color = BufferObj::create_uniform_buffer(&device, &[1.0_f32, 0.5, 0.0, 1.0], None);
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let _node = BufferlessFullscreenNode::from_descriptor(
+            &device,
+            BufferlessFullscreenDescriptor {
+                uniforms: vec![&color],
+                ..BufferlessFullscreenDescriptor::new(TextureFormat::Rgba8Unorm, &shader_module)
+                    .with_entry_points("vs_fullscreen", "fs_solid")
+            },
+        );
+        let error = crate::test_util::block_on(device.pop_error_scope());
+        assert!(error.is_none(), "{:?}", error);
+    }
+}
@@ -1,6 +1,7 @@
 use wgpu::{PushConstantRange, ShaderModule, StorageTextureAccess};
 
-use super::{BindingGroupSetting, DynamicUniformBindingGroup};
+use super::{BindingGroupSetting, BindingResourceRef, BindingSlot, DynamicUniformBindingGroup};
+use crate::shader::{ShaderError, ShaderReflection};
 use crate::{buffer::BufferObj, AnyTexture};
 
 use core::ops::Range;
@@ -13,6 +14,8 @@ pub struct ComputeNode {
     pub pipeline_layout: wgpu::PipelineLayout,
     pub pipeline: wgpu::ComputePipeline,
     pub group_count: (u32, u32, u32),
+    // 重建 pipeline 时使用
+    entry_point: String,
 }
 
 #[allow(dead_code)]
@@ -61,13 +64,39 @@ impl ComputeNode {
             entry_point: "cs_main",
         });
 
-        ComputeNode { bg_setting, dy_uniform_bg: Some(dy_uniform_bg), pipeline_layout, pipeline, group_count }
+        ComputeNode {
+            bg_setting,
+            dy_uniform_bg: Some(dy_uniform_bg),
+            pipeline_layout,
+            pipeline,
+            group_count,
+            entry_point: "cs_main".to_string(),
+        }
     }
 
     pub fn new_with_push_constants(
         device: &wgpu::Device, group_count: (u32, u32, u32), uniforms: Vec<&BufferObj>,
         storage_buffers: Vec<&BufferObj>, inout_tv: Vec<(&AnyTexture, Option<StorageTextureAccess>)>,
         shader_module: &ShaderModule, push_constants: Option<Vec<(wgpu::ShaderStages, Range<u32>)>>,
+    ) -> Self {
+        ComputeNode::new_with_entry_point(
+            device,
+            group_count,
+            uniforms,
+            storage_buffers,
+            inout_tv,
+            shader_module,
+            push_constants,
+            "cs_main",
+        )
+    }
+
+    /// 同一个着色器模块中有多个 compute 入口时，指定使用其中的 `entry_point`
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_with_entry_point(
+        device: &wgpu::Device, group_count: (u32, u32, u32), uniforms: Vec<&BufferObj>,
+        storage_buffers: Vec<&BufferObj>, inout_tv: Vec<(&AnyTexture, Option<StorageTextureAccess>)>,
+        shader_module: &ShaderModule, push_constants: Option<Vec<(wgpu::ShaderStages, Range<u32>)>>, entry_point: &str,
     ) -> Self {
         let mut visibilitys: Vec<wgpu::ShaderStages> = vec![];
         for _ in 0..(uniforms.len() + storage_buffers.len() + inout_tv.len()) {
@@ -91,10 +120,63 @@ impl ComputeNode {
             label: None,
             layout: Some(&pipeline_layout),
             module: shader_module,
-            entry_point: "cs_main",
+            entry_point,
         });
 
-        ComputeNode { bg_setting, dy_uniform_bg: None, pipeline_layout, pipeline, group_count }
+        ComputeNode {
+            bg_setting,
+            dy_uniform_bg: None,
+            pipeline_layout,
+            pipeline,
+            group_count,
+            entry_point: entry_point.to_string(),
+        }
+    }
+
+    /// group 0 的布局由着色器反射信息生成，资源按变量名或 `@binding` 序号对应
+    pub fn from_reflection(
+        device: &wgpu::Device, group_count: (u32, u32, u32), reflection: &ShaderReflection,
+        bindings: &[(BindingSlot, BindingResourceRef)], shader_module: &ShaderModule, entry_point: &str,
+    ) -> Result<Self, ShaderError> {
+        let bg_setting = BindingGroupSetting::from_reflection(device, reflection, 0, bindings)?;
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bg_setting.bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            module: shader_module,
+            entry_point,
+        });
+
+        Ok(ComputeNode {
+            bg_setting,
+            dy_uniform_bg: None,
+            pipeline_layout,
+            pipeline,
+            group_count,
+            entry_point: entry_point.to_string(),
+        })
+    }
+
+    /// 着色器热重载后，用新的 module 原地重建 pipeline，入口函数、布局与 bind group 保持不变
+    pub fn update_shader_module(&mut self, device: &wgpu::Device, shader_module: &ShaderModule) {
+        self.pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: None,
+            layout: Some(&self.pipeline_layout),
+            module: shader_module,
+            entry_point: &self.entry_point,
+        });
+    }
+
+    /// GrowableBuffer 扩容后只重建 bind group，pipeline 保持不变，见 [`BindingGroupSetting::refresh_bind_group`]
+    pub fn refresh_bind_group(
+        &mut self, device: &wgpu::Device, resources: &[(u32, BindingResourceRef)],
+    ) -> Result<bool, ShaderError> {
+        self.bg_setting.refresh_bind_group(device, resources)
     }
 
     pub fn compute(&self, encoder: &mut wgpu::CommandEncoder) {
@@ -112,6 +194,31 @@ impl ComputeNode {
         self.dispatch_by_offsets(&mut cpass, offsets);
     }
 
+    /// 按元素序号依次 dispatch，偏移由动态 uniform buffer 的 stride 计算
+    pub fn compute_by_indices(&self, encoder: &mut wgpu::CommandEncoder, indices: &[u32]) {
+        let offsets = self.dy_uniform_bg.as_ref().map(|bg| indices.iter().map(|i| bg.offsets(*i)).collect());
+        self.compute_by_offsets(encoder, offsets);
+    }
+
+    /// 工作组数取自 `indirect` buffer 中 `offset` 处的 [`DispatchIndirectArgs`](crate::DispatchIndirectArgs)
+    pub fn compute_indirect(
+        &self, encoder: &mut wgpu::CommandEncoder, indirect: &BufferObj, indirect_offset: wgpu::BufferAddress,
+    ) {
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
+        self.dispatch_indirect(&mut cpass, indirect, indirect_offset);
+    }
+
+    pub fn dispatch_indirect<'a, 'b: 'a>(
+        &'b self, cpass: &mut wgpu::ComputePass<'a>, indirect: &'a BufferObj, indirect_offset: wgpu::BufferAddress,
+    ) {
+        cpass.set_pipeline(&self.pipeline);
+        cpass.set_bind_group(0, &self.bg_setting.bind_group, &[]);
+        if let Some(dy_uniform_bg) = &self.dy_uniform_bg {
+            cpass.set_bind_group(1, &dy_uniform_bg.bind_group, &dy_uniform_bg.offsets(0));
+        }
+        cpass.dispatch_workgroups_indirect(&indirect.buffer, indirect_offset);
+    }
+
     pub fn dispatch_by_offsets<'a, 'b: 'a>(
         &'b self, cpass: &mut wgpu::ComputePass<'a>, offsets: Option<Vec<Vec<wgpu::DynamicOffset>>>,
     ) {
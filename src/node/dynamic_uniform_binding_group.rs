@@ -5,6 +5,8 @@ use std::vec::Vec;
 pub struct DynamicUniformBindingGroup {
     pub bind_group_layout: wgpu::BindGroupLayout,
     pub bind_group: wgpu::BindGroup,
+    /// 每个 binding 相邻两个元素之间的字节数
+    pub strides: Vec<wgpu::DynamicOffset>,
 }
 
 impl DynamicUniformBindingGroup {
@@ -12,9 +14,15 @@ impl DynamicUniformBindingGroup {
         let mut layouts: Vec<wgpu::BindGroupLayoutEntry> = vec![];
         let mut entries: Vec<wgpu::BindGroupEntry> = vec![];
 
+        let mut strides: Vec<wgpu::DynamicOffset> = vec![];
+        let alignment = device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+
         let mut b_index = 0;
         for i in 0..uniforms.len() {
             let buffer_obj = uniforms[i];
+            // 未指定 min_binding_size 的 buffer 使用一个对齐单位大小的窗口
+            let window = buffer_obj.0.min_binding_size.map_or(alignment, |size| size.get());
+            strides.push((((window + alignment - 1) / alignment) * alignment) as wgpu::DynamicOffset);
 
             layouts.push(wgpu::BindGroupLayoutEntry {
                 binding: b_index,
@@ -22,8 +30,7 @@ impl DynamicUniformBindingGroup {
                 ty: wgpu::BindingType::Buffer {
                     ty: wgpu::BufferBindingType::Uniform,
                     has_dynamic_offset: true,
-                    // min_binding_size: buffer_obj.0.min_binding_size,
-                    min_binding_size: None,
+                    min_binding_size: wgpu::BufferSize::new(window),
                 },
                 count: None,
             });
@@ -36,10 +43,7 @@ impl DynamicUniformBindingGroup {
                 resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
                     buffer: &buffer_obj.0.buffer,
                     offset: 0,
-                    // size: buffer_obj.0.min_binding_size,
-                    size: wgpu::BufferSize::new(
-                        device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress,
-                    ),
+                    size: wgpu::BufferSize::new(window),
                 }),
             });
             b_index += 1;
@@ -53,6 +57,11 @@ impl DynamicUniformBindingGroup {
             label: None,
         });
 
-        DynamicUniformBindingGroup { bind_group_layout, bind_group }
+        DynamicUniformBindingGroup { bind_group_layout, bind_group, strides }
+    }
+
+    /// 所有 binding 都使用第 `index` 个元素时的动态偏移
+    pub fn offsets(&self, index: u32) -> Vec<wgpu::DynamicOffset> {
+        self.strides.iter().map(|stride| stride * index).collect()
     }
 }
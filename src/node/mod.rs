@@ -1,8 +1,11 @@
 mod compute_node;
 pub use compute_node::ComputeNode;
 
+mod multi_entry_compute_node;
+pub use multi_entry_compute_node::MultiEntryComputeNode;
+
 mod binding_group_setting;
-pub use binding_group_setting::BindingGroupSetting;
+pub use binding_group_setting::{BindingGroupSetting, BindingResourceRef, BindingSlot, OwnedResource};
 
 mod dynamic_uniform_binding_group;
 pub use dynamic_uniform_binding_group::DynamicUniformBindingGroup;
@@ -10,4 +13,4 @@ pub use dynamic_uniform_binding_group::DynamicUniformBindingGroup;
 mod view_node;
 pub use view_node::{ViewNode, ViewNodeBuilder};
 mod bufferless_fullscreen_node;
-pub use bufferless_fullscreen_node::BufferlessFullscreenNode;
+pub use bufferless_fullscreen_node::{BufferlessFullscreenDescriptor, BufferlessFullscreenNode};
@@ -0,0 +1,122 @@
+use wgpu::{ShaderModule, StorageTextureAccess};
+
+use super::{BindingGroupSetting, BindingResourceRef, BindingSlot};
+use crate::shader::{ShaderError, ShaderReflection};
+use crate::{buffer::BufferObj, AnyTexture};
+
+use std::vec::Vec;
+
+/// 同一个着色器模块中的多个 compute 入口共用一个 bind group，
+/// 每个入口一个 pipeline 及各自的 workgroup 数量，按给定的顺序依次 dispatch。
+/// 如格子玻尔兹曼的一步：`collide` -> `stream` -> `boundary`，其中 `boundary` 只需处理边界格子
+#[allow(dead_code)]
+pub struct MultiEntryComputeNode {
+    pub bg_setting: BindingGroupSetting,
+    pub pipeline_layout: wgpu::PipelineLayout,
+    /// (入口名, workgroup 数量, pipeline)
+    pub pipelines: Vec<(String, (u32, u32, u32), wgpu::ComputePipeline)>,
+}
+
+#[allow(dead_code)]
+impl MultiEntryComputeNode {
+    /// `entry_points` 为入口名及其 dispatch 的 workgroup 数量
+    pub fn new(
+        device: &wgpu::Device, uniforms: Vec<&BufferObj>, storage_buffers: Vec<&BufferObj>,
+        inout_tv: Vec<(&AnyTexture, Option<StorageTextureAccess>)>, shader_module: &ShaderModule,
+        entry_points: &[(&str, (u32, u32, u32))],
+    ) -> Self {
+        let mut visibilitys: Vec<wgpu::ShaderStages> = vec![];
+        for _ in 0..(uniforms.len() + storage_buffers.len() + inout_tv.len()) {
+            visibilitys.push(wgpu::ShaderStages::COMPUTE);
+        }
+        let bg_setting = BindingGroupSetting::new(device, uniforms, storage_buffers, inout_tv, vec![], visibilitys);
+        Self::from_bg_setting(device, bg_setting, shader_module, entry_points)
+    }
+
+    /// group 0 的布局由着色器反射信息生成，资源按变量名或 `@binding` 序号对应
+    pub fn from_reflection(
+        device: &wgpu::Device, reflection: &ShaderReflection, bindings: &[(BindingSlot, BindingResourceRef)],
+        shader_module: &ShaderModule, entry_points: &[(&str, (u32, u32, u32))],
+    ) -> Result<Self, ShaderError> {
+        let bg_setting = BindingGroupSetting::from_reflection(device, reflection, 0, bindings)?;
+        Ok(Self::from_bg_setting(device, bg_setting, shader_module, entry_points))
+    }
+
+    fn from_bg_setting(
+        device: &wgpu::Device, bg_setting: BindingGroupSetting, shader_module: &ShaderModule,
+        entry_points: &[(&str, (u32, u32, u32))],
+    ) -> Self {
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bg_setting.bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipelines = entry_points
+            .iter()
+            .map(|(entry_point, group_count)| {
+                let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                    label: Some(entry_point),
+                    layout: Some(&pipeline_layout),
+                    module: shader_module,
+                    entry_point,
+                });
+                (entry_point.to_string(), *group_count, pipeline)
+            })
+            .collect();
+
+        MultiEntryComputeNode { bg_setting, pipeline_layout, pipelines }
+    }
+
+    /// 着色器热重载后，用新的 module 原地重建所有入口的 pipeline
+    pub fn update_shader_module(&mut self, device: &wgpu::Device, shader_module: &ShaderModule) {
+        for (entry_point, _, pipeline) in self.pipelines.iter_mut() {
+            *pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&self.pipeline_layout),
+                module: shader_module,
+                entry_point,
+            });
+        }
+    }
+
+    /// GrowableBuffer 扩容后只重建 bind group，pipeline 保持不变，见 [`BindingGroupSetting::refresh_bind_group`]
+    pub fn refresh_bind_group(
+        &mut self, device: &wgpu::Device, resources: &[(u32, BindingResourceRef)],
+    ) -> Result<bool, ShaderError> {
+        self.bg_setting.refresh_bind_group(device, resources)
+    }
+
+    pub fn compute(&self, encoder: &mut wgpu::CommandEncoder) {
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
+        self.dispatch(&mut cpass);
+    }
+
+    /// 画布尺寸变化等情况下更新入口的 workgroup 数量，不存在此入口时返回 false
+    pub fn set_group_count(&mut self, entry_point: &str, group_count: (u32, u32, u32)) -> bool {
+        match self.pipelines.iter_mut().find(|(name, ..)| name == entry_point) {
+            Some((_, count, _)) => {
+                *count = group_count;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 按顺序 dispatch 所有入口，每个入口使用各自的 workgroup 数量
+    pub fn dispatch<'a, 'b: 'a>(&'b self, cpass: &mut wgpu::ComputePass<'a>) {
+        cpass.set_bind_group(0, &self.bg_setting.bind_group, &[]);
+        for (_, group_count, pipeline) in self.pipelines.iter() {
+            cpass.set_pipeline(pipeline);
+            cpass.dispatch_workgroups(group_count.0, group_count.1, group_count.2);
+        }
+    }
+
+    /// 只 dispatch 名为 `entry_point` 的入口，不存在时什么也不做
+    pub fn dispatch_entry<'a, 'b: 'a>(&'b self, cpass: &mut wgpu::ComputePass<'a>, entry_point: &str) {
+        if let Some((_, group_count, pipeline)) = self.pipelines.iter().find(|(name, ..)| name == entry_point) {
+            cpass.set_pipeline(pipeline);
+            cpass.set_bind_group(0, &self.bg_setting.bind_group, &[]);
+            cpass.dispatch_workgroups(group_count.0, group_count.1, group_count.2);
+        }
+    }
+}
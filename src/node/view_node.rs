@@ -1,6 +1,7 @@
 use crate::geometry::Plane;
 use crate::math::{Position, Rect, Size};
-use crate::node::BindingGroupSetting;
+use crate::node::{BindingGroupSetting, BindingResourceRef, BindingSlot, OwnedResource};
+use crate::shader::{ShaderError, ShaderReflection};
 use crate::vertex::Vertex;
 use crate::{AnyTexture, BufferObj, MVPUniform};
 use bytemuck::Pod;
@@ -26,7 +27,12 @@ pub struct NodeAttributes<'a, T: Vertex + Pod> {
     pub cull_mode: Option<wgpu::Face>,
     pub use_depth_stencil: bool,
     pub shader_module: &'a wgpu::ShaderModule,
+    pub vs_entry_point: &'a str,
+    pub fs_entry_point: &'a str,
     pub shader_stages: Vec<wgpu::ShaderStages>,
+    // 设置后 group 0 的布局由着色器反射信息生成，忽略上面手动设置的资源及 shader_stages
+    pub reflection: Option<&'a ShaderReflection>,
+    pub bindings: Vec<(BindingSlot<'a>, BindingResourceRef<'a>)>,
 }
 
 pub struct ViewNodeBuilder<'a, T: Vertex + Pod> {
@@ -68,7 +74,11 @@ impl<'a, T: Vertex + Pod> ViewNodeBuilder<'a, T> {
                 cull_mode: Some(wgpu::Face::Back),
                 use_depth_stencil: false,
                 shader_module,
+                vs_entry_point: "vs_main",
+                fs_entry_point: "fs_main",
                 shader_stages: vec![],
+                reflection: None,
+                bindings: vec![],
             },
         }
     }
@@ -152,12 +162,41 @@ impl<'a, T: Vertex + Pod> ViewNodeBuilder<'a, T> {
         self
     }
 
+    pub fn with_entry_points(mut self, vs_entry_point: &'a str, fs_entry_point: &'a str) -> Self {
+        self.vs_entry_point = vs_entry_point;
+        self.fs_entry_point = fs_entry_point;
+        self
+    }
+
     pub fn with_shader_stages(mut self, states: Vec<wgpu::ShaderStages>) -> Self {
         self.shader_stages = states;
         self
     }
 
+    /// 按着色器反射信息绑定资源，`bindings` 中的资源按变量名或 `@binding` 序号对应；
+    /// 没有提供的采样器会使用默认采样器
+    pub fn with_reflection(
+        mut self,
+        reflection: &'a ShaderReflection,
+        bindings: Vec<(BindingSlot<'a>, BindingResourceRef<'a>)>,
+    ) -> Self {
+        self.reflection = Some(reflection);
+        self.bindings = bindings;
+        self
+    }
+
     pub fn build(self, device: &wgpu::Device) -> ViewNode {
+        match self.try_build(device) {
+            Ok(node) => node,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// 与 `build` 相同，设置了反射信息时，资源与着色器声明不一致会返回错误
+    pub fn try_build(self, device: &wgpu::Device) -> Result<ViewNode, ShaderError> {
+        if self.reflection.is_some() {
+            return ViewNode::frome_attributes::<T>(self.attributes, device);
+        }
         debug_assert!(
             self.shader_stages.len()
                 >= self.uniform_buffers.len()
@@ -178,6 +217,7 @@ pub struct ViewNode {
     pub bg_setting: BindingGroupSetting,
     pub dy_uniform_bg: Option<super::DynamicUniformBindingGroup>,
     pub pipeline: wgpu::RenderPipeline,
+    pipeline_state: RenderPipelineState,
     view_width: f32,
     view_height: f32,
     pub clear_color: wgpu::Color,
@@ -188,7 +228,7 @@ impl ViewNode {
     fn frome_attributes<T: Vertex + Pod>(
         attributes: NodeAttributes<T>,
         device: &wgpu::Device,
-    ) -> Self {
+    ) -> Result<Self, ShaderError> {
         let corlor_format = if let Some(format) = attributes.corlor_format {
             format
         } else {
@@ -214,37 +254,80 @@ impl ViewNode {
             stages
         };
 
-        let sampler = crate::load_texture::default_sampler(device);
-        let new_samplers: Vec<&wgpu::Sampler> = if attributes.tex_views.len() > 0 {
-            if attributes.samplers.len() > 0 {
-                attributes.samplers
-            } else {
-                vec![&sampler]
+        let bg_setting = if let Some(reflection) = attributes.reflection {
+            // 没有提供的采样器由节点创建并持有
+            let default_samplers: Vec<(u32, wgpu::Sampler)> = reflection
+                .group_bindings(0)
+                .filter(|b| {
+                    let provided = attributes.bindings.iter().any(|(slot, _)| match slot {
+                        BindingSlot::Name(name) => b.name.as_deref() == Some(*name),
+                        BindingSlot::Index(index) => *index == b.binding,
+                    });
+                    !provided && matches!(b.ty, wgpu::BindingType::Sampler(_))
+                })
+                .map(|b| (b.binding, crate::load_texture::default_sampler(device)))
+                .collect();
+            let mut bindings = attributes.bindings.clone();
+            for (binding, sampler) in default_samplers.iter() {
+                bindings.push((BindingSlot::Index(*binding), BindingResourceRef::Sampler(sampler)));
+            }
+            let mut bg_setting =
+                BindingGroupSetting::from_reflection(device, reflection, 0, &bindings)?;
+            for (binding, sampler) in default_samplers {
+                bg_setting.own_resource(binding, OwnedResource::Sampler(sampler));
             }
+            bg_setting
         } else {
-            vec![]
-        };
-        // 如果没有设置 mvp, 且设置了 view_size, 则设置一个全屏的 mvp
-        let (p_matrix, vm_matrix, _factor) =
-            crate::matrix_helper::perspective_mvp(attributes.view_size);
-        let mvp = MVPUniform {
-            mvp_matrix: (p_matrix * vm_matrix).into(),
-        };
-        let mvp_buf = BufferObj::create_uniform_buffer(device, &mvp, Some("mvp uniform"));
-        let uniform_buffers =
-            if attributes.uniform_buffers.len() == 0 && attributes.view_size.width > 0.0 {
-                vec![&mvp_buf]
+            let use_default_sampler = !attributes.tex_views.is_empty() && attributes.samplers.is_empty();
+            let sampler = if use_default_sampler {
+                Some(crate::load_texture::default_sampler(device))
+            } else {
+                None
+            };
+            let new_samplers: Vec<&wgpu::Sampler> = if let Some(sampler) = &sampler {
+                vec![sampler]
+            } else if attributes.tex_views.len() > 0 {
+                attributes.samplers
+            } else {
+                vec![]
+            };
+            // 如果没有设置 mvp, 且设置了 view_size, 则设置一个全屏的 mvp
+            let use_mvp_buf =
+                attributes.uniform_buffers.is_empty() && attributes.view_size.width > 0.0;
+            let mvp_buf = if use_mvp_buf {
+                let (p_matrix, vm_matrix, _factor) =
+                    crate::matrix_helper::perspective_mvp(attributes.view_size);
+                let mvp = MVPUniform {
+                    mvp_matrix: (p_matrix * vm_matrix).into(),
+                };
+                Some(BufferObj::create_uniform_buffer(device, &mvp, Some("mvp uniform")))
+            } else {
+                None
+            };
+            let uniform_buffers = if let Some(mvp_buf) = &mvp_buf {
+                vec![mvp_buf]
             } else {
                 attributes.uniform_buffers
             };
-        let bg_setting = BindingGroupSetting::new(
-            device,
-            uniform_buffers,
-            attributes.storage_buffers,
-            attributes.tex_views,
-            new_samplers,
-            stages,
-        );
+            let sampler_binding = uniform_buffers.len()
+                + attributes.storage_buffers.len()
+                + attributes.tex_views.len();
+            let mut bg_setting = BindingGroupSetting::new(
+                device,
+                uniform_buffers,
+                attributes.storage_buffers,
+                attributes.tex_views,
+                new_samplers,
+                stages,
+            );
+            if let Some(mvp_buf) = mvp_buf {
+                bg_setting.own_resource(0, OwnedResource::Buffer(mvp_buf));
+            }
+            if let Some(sampler) = sampler {
+                bg_setting.own_resource(sampler_binding as u32, OwnedResource::Sampler(sampler));
+            }
+            bg_setting
+        };
 
         // Create the vertex and index buffers
         let (vertex_buf, index_data) = if let Some(vi) = attributes.vertices_and_indices {
@@ -324,24 +407,14 @@ impl ViewNode {
             (None, pipeline_layout)
         };
 
-        // Create the render pipeline
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("image_view pipeline"),
-            layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &attributes.shader_module,
-                entry_point: "vs_main",
-                buffers: &vertex_buffer_layouts,
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &attributes.shader_module,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: corlor_format,
-                    blend: attributes.color_blend_state,
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
+        let pipeline_state = RenderPipelineState {
+            pipeline_layout,
+            vertex_buffers: vertex_buffer_layouts
+                .iter()
+                .map(|layout| (layout.array_stride, layout.step_mode, layout.attributes.to_vec()))
+                .collect(),
+            color_format: corlor_format,
+            color_blend_state: attributes.color_blend_state,
             primitive: wgpu::PrimitiveState {
                 topology: attributes.primitive_topology,
                 front_face: wgpu::FrontFace::Ccw,
@@ -349,17 +422,13 @@ impl ViewNode {
                 polygon_mode: wgpu::PolygonMode::Fill,
                 ..Default::default()
             },
-            // ??????
-            depth_stencil: if attributes.use_depth_stencil {
-                Some(crate::depth_stencil::create_state())
-            } else {
-                None
-            },
-            multisample: wgpu::MultisampleState::default(),
-            multiview: None,
-        });
+            use_depth_stencil: attributes.use_depth_stencil,
+            vs_entry_point: attributes.vs_entry_point.to_string(),
+            fs_entry_point: attributes.fs_entry_point.to_string(),
+        };
+        let pipeline = pipeline_state.create_pipeline(device, attributes.shader_module);
 
-        ViewNode {
+        Ok(ViewNode {
             view_width: attributes.view_size.width,
             view_height: attributes.view_size.height,
             vertex_buf,
@@ -368,8 +437,24 @@ impl ViewNode {
             bg_setting,
             dy_uniform_bg,
             pipeline,
+            pipeline_state,
             clear_color: crate::utils::alpha_color(),
-        }
+        })
+    }
+
+    /// 着色器热重载后，用新的 module 原地重建 pipeline，布局与其它状态保持不变
+    pub fn update_shader_module(&mut self, device: &wgpu::Device, shader_module: &wgpu::ShaderModule) {
+        self.pipeline = self.pipeline_state.create_pipeline(device, shader_module);
+    }
+
+    /// GrowableBuffer 扩容后只重建 bind group，pipeline 保持不变，
+    /// 默认的 mvp uniform 及采样器由节点提供，见 [`BindingGroupSetting::refresh_bind_group`]
+    pub fn refresh_bind_group(
+        &mut self,
+        device: &wgpu::Device,
+        resources: &[(u32, BindingResourceRef)],
+    ) -> Result<bool, ShaderError> {
+        self.bg_setting.refresh_bind_group(device, resources)
     }
 
     // 视口的宽高发生变化
@@ -447,12 +532,108 @@ impl ViewNode {
     ) {
         self.set_rpass(rpass);
         if let Some(node) = &self.dy_uniform_bg {
-            rpass.set_bind_group(
-                1,
-                &node.bind_group,
-                &[256 * offset_index as wgpu::DynamicOffset],
-            );
+            rpass.set_bind_group(1, &node.bind_group, &node.offsets(offset_index));
+        }
+        rpass.draw_indexed(0..self.index_count as u32, 0, 0..instance_count);
+    }
+
+    // 绘制参数取自 indirect buffer 中 indirect_offset 处的 DrawIndexedIndirectArgs
+    pub fn draw_indexed_indirect<'a, 'b: 'a>(
+        &'b self,
+        rpass: &mut wgpu::RenderPass<'b>,
+        indirect: &'b BufferObj,
+        indirect_offset: wgpu::BufferAddress,
+    ) {
+        self.set_rpass(rpass);
+        if let Some(node) = &self.dy_uniform_bg {
+            rpass.set_bind_group(1, &node.bind_group, &node.offsets(0));
+        }
+        rpass.draw_indexed_indirect(&indirect.buffer, indirect_offset);
+    }
+
+    // 不使用索引，绘制参数取自 indirect buffer 中 indirect_offset 处的 DrawIndirectArgs
+    pub fn draw_indirect<'a, 'b: 'a>(
+        &'b self,
+        rpass: &mut wgpu::RenderPass<'b>,
+        indirect: &'b BufferObj,
+        indirect_offset: wgpu::BufferAddress,
+    ) {
+        self.set_rpass(rpass);
+        if let Some(node) = &self.dy_uniform_bg {
+            rpass.set_bind_group(1, &node.bind_group, &node.offsets(0));
+        }
+        rpass.draw_indirect(&indirect.buffer, indirect_offset);
+    }
+
+    // 直接使用给定的动态偏移，如 FrameRingBuffer 分配返回的偏移
+    pub fn draw_rpass_with_offsets<'a, 'b: 'a>(
+        &'b self,
+        rpass: &mut wgpu::RenderPass<'b>,
+        offsets: &[wgpu::DynamicOffset],
+        instance_count: u32,
+    ) {
+        self.set_rpass(rpass);
+        if let Some(node) = &self.dy_uniform_bg {
+            rpass.set_bind_group(1, &node.bind_group, offsets);
         }
         rpass.draw_indexed(0..self.index_count as u32, 0, 0..instance_count);
     }
 }
+
+// 重建 pipeline 所需的状态
+struct RenderPipelineState {
+    pipeline_layout: wgpu::PipelineLayout,
+    vertex_buffers: Vec<(wgpu::BufferAddress, wgpu::VertexStepMode, Vec<wgpu::VertexAttribute>)>,
+    color_format: wgpu::TextureFormat,
+    color_blend_state: Option<wgpu::BlendState>,
+    primitive: wgpu::PrimitiveState,
+    use_depth_stencil: bool,
+    vs_entry_point: String,
+    fs_entry_point: String,
+}
+
+impl RenderPipelineState {
+    fn create_pipeline(
+        &self,
+        device: &wgpu::Device,
+        shader_module: &wgpu::ShaderModule,
+    ) -> wgpu::RenderPipeline {
+        let vertex_buffer_layouts: Vec<wgpu::VertexBufferLayout> = self
+            .vertex_buffers
+            .iter()
+            .map(|(array_stride, step_mode, attributes)| wgpu::VertexBufferLayout {
+                array_stride: *array_stride,
+                step_mode: *step_mode,
+                attributes,
+            })
+            .collect();
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("image_view pipeline"),
+            layout: Some(&self.pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: shader_module,
+                entry_point: &self.vs_entry_point,
+                buffers: &vertex_buffer_layouts,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader_module,
+                entry_point: &self.fs_entry_point,
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: self.color_format,
+                    blend: self.color_blend_state,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: self.primitive,
+            // ??????
+            depth_stencil: if self.use_depth_stencil {
+                Some(crate::depth_stencil::create_state())
+            } else {
+                None
+            },
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        })
+    }
+}
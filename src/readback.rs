@@ -0,0 +1,235 @@
+use crate::{AnyTexture, BufferError, BufferObj};
+use bytemuck::Pod;
+#[cfg(target_arch = "wasm32")]
+use std::future::Future;
+use std::num::NonZeroU32;
+#[cfg(target_arch = "wasm32")]
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::Waker;
+#[cfg(target_arch = "wasm32")]
+use std::task::{Context, Poll};
+
+type Finish<R> = Box<dyn FnOnce(&[u8]) -> R + Send>;
+
+// map_async 回调与 future 之间共享的状态
+#[derive(Default)]
+struct MapState {
+    result: Option<Result<(), wgpu::BufferAsyncError>>,
+    waker: Option<Waker>,
+}
+
+/// 进行中的 GPU 数据回读
+///
+/// native 上 `map_async` 的回调只会在 `device.poll` 中触发，使用 [`ReadbackFuture::wait`] 推进 device 并等待结果；
+/// wasm 上映射由浏览器的事件循环完成，此类型实现了 `Future`，映射完成时由回调唤醒，可以交给 `spawn_local`
+pub struct ReadbackFuture<R> {
+    staging: Option<wgpu::Buffer>,
+    state: Arc<Mutex<MapState>>,
+    finish: Option<Finish<R>>,
+    error: Option<BufferError>,
+}
+
+impl<R> ReadbackFuture<R> {
+    fn new(staging: wgpu::Buffer, finish: Finish<R>) -> Self {
+        let state = Arc::new(Mutex::new(MapState::default()));
+        let shared = state.clone();
+        staging.slice(..).map_async(wgpu::MapMode::Read, move |r| {
+            let waker = {
+                let mut state = shared.lock().unwrap();
+                state.result = Some(r);
+                state.waker.take()
+            };
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        });
+        ReadbackFuture { staging: Some(staging), state, finish: Some(finish), error: None }
+    }
+
+    fn failed(error: BufferError) -> Self {
+        ReadbackFuture { staging: None, state: Default::default(), finish: None, error: Some(error) }
+    }
+
+    // 映射完成后取出数据，未完成时记下 waker 并返回 None
+    fn try_finish(&mut self, waker: Option<&Waker>) -> Option<Result<R, BufferError>> {
+        if let Some(error) = self.error.take() {
+            return Some(Err(error));
+        }
+        let mapped = {
+            let mut state = self.state.lock().unwrap();
+            match state.result.take() {
+                Some(mapped) => mapped,
+                None => {
+                    state.waker = waker.cloned();
+                    return None;
+                }
+            }
+        };
+        let staging = self.staging.take().unwrap();
+        if mapped.is_err() {
+            return Some(Err(BufferError::MapFailed));
+        }
+        let result = {
+            let data = staging.slice(..).get_mapped_range();
+            (self.finish.take().unwrap())(&data)
+        };
+        staging.unmap();
+        Some(Ok(result))
+    }
+
+    /// 阻塞等待回读完成
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn wait(mut self, device: &wgpu::Device) -> Result<R, BufferError> {
+        loop {
+            if let Some(result) = self.try_finish(None) {
+                return result;
+            }
+            device.poll(wgpu::Maintain::Wait);
+        }
+    }
+}
+
+impl<R> Unpin for ReadbackFuture<R> {}
+
+#[cfg(target_arch = "wasm32")]
+impl<R> Future for ReadbackFuture<R> {
+    type Output = Result<R, BufferError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.try_finish(Some(cx.waker())) {
+            Some(result) => Poll::Ready(result),
+            None => Poll::Pending,
+        }
+    }
+}
+
+fn create_staging_buffer(device: &wgpu::Device, size: wgpu::BufferAddress) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("readback staging buffer"),
+        size,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+#[allow(dead_code)]
+impl BufferObj {
+    /// 将整个 buffer 的内容回读为 `Vec<T>`，buffer 需要带有 `COPY_SRC`
+    /// （如 `create_empty_storage_buffer(.., can_read_back: true, ..)`）
+    pub fn read_back<T: Pod>(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> ReadbackFuture<Vec<T>> {
+        if !self.buffer.usage().contains(wgpu::BufferUsages::COPY_SRC) {
+            return ReadbackFuture::failed(BufferError::MissingUsage(wgpu::BufferUsages::COPY_SRC));
+        }
+        // 复制的大小需要是 4 的倍数，`create_buffer_init` 创建的 buffer 实际大小已按此对齐，多出的部分在回读后截掉
+        let copy_size = align_to(self.size, wgpu::COPY_BUFFER_ALIGNMENT);
+        let staging = create_staging_buffer(device, copy_size);
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("readback") });
+        encoder.copy_buffer_to_buffer(&self.buffer, 0, &staging, 0, copy_size);
+        queue.submit(Some(encoder.finish()));
+
+        let len = self.size as usize / std::mem::size_of::<T>();
+        ReadbackFuture::new(
+            staging,
+            Box::new(move |data| {
+                // 映射的内存不一定满足 T 的对齐要求，复制到新分配的 Vec 中
+                let mut out = vec![T::zeroed(); len];
+                let bytes: &mut [u8] = bytemuck::cast_slice_mut(&mut out);
+                let count = bytes.len();
+                bytes.copy_from_slice(&data[..count]);
+                out
+            }),
+        )
+    }
+
+    /// `read_back` 的阻塞版本
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn read_back_blocking<T: Pod>(
+        &self, device: &wgpu::Device, queue: &wgpu::Queue,
+    ) -> Result<Vec<T>, BufferError> {
+        self.read_back(device, queue).wait(device)
+    }
+}
+
+#[allow(dead_code)]
+impl AnyTexture {
+    /// 回读 mip 0 的所有像素，去除 `bytes_per_row` 按 256 字节对齐的填充后，按行紧密排列返回。
+    /// 纹理需要带有 `COPY_SRC`，不支持压缩格式
+    pub fn read_back(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> ReadbackFuture<Vec<u8>> {
+        let info = self.format.describe();
+        if info.block_dimensions != (1, 1) {
+            return ReadbackFuture::failed(BufferError::UnsupportedFormat(self.format));
+        }
+        let unpadded_bytes_per_row = self.size.width * info.block_size as u32;
+        let padded_bytes_per_row =
+            align_to(unpadded_bytes_per_row as u64, wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as u64) as u32;
+        let rows = self.size.height * self.size.depth_or_array_layers;
+
+        let staging = create_staging_buffer(device, padded_bytes_per_row as wgpu::BufferAddress * rows as u64);
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("readback") });
+        encoder.copy_texture_to_buffer(
+            self.tex.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &staging,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(padded_bytes_per_row),
+                    rows_per_image: NonZeroU32::new(self.size.height),
+                },
+            },
+            self.size,
+        );
+        queue.submit(Some(encoder.finish()));
+
+        ReadbackFuture::new(
+            staging,
+            Box::new(move |data| {
+                unpad_rows(data, padded_bytes_per_row as usize, unpadded_bytes_per_row as usize, rows as usize)
+            }),
+        )
+    }
+
+    /// `read_back` 的阻塞版本
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn read_back_blocking(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<Vec<u8>, BufferError> {
+        self.read_back(device, queue).wait(device)
+    }
+}
+
+fn align_to(value: u64, alignment: u64) -> u64 {
+    (value + alignment - 1) / alignment * alignment
+}
+
+// 去除每行末尾为满足 `bytes_per_row` 对齐而添加的填充
+fn unpad_rows(data: &[u8], padded_bytes_per_row: usize, unpadded_bytes_per_row: usize, rows: usize) -> Vec<u8> {
+    let mut texels = Vec::with_capacity(unpadded_bytes_per_row * rows);
+    for row in data.chunks(padded_bytes_per_row).take(rows) {
+        texels.extend_from_slice(&row[..unpadded_bytes_per_row]);
+    }
+    texels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aligns_copy_sizes() {
+        assert_eq!(align_to(0, wgpu::COPY_BUFFER_ALIGNMENT), 0);
+        assert_eq!(align_to(6, wgpu::COPY_BUFFER_ALIGNMENT), 8);
+        assert_eq!(align_to(8, wgpu::COPY_BUFFER_ALIGNMENT), 8);
+        assert_eq!(align_to(3 * 4, wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as u64), 256);
+        assert_eq!(align_to(256, wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as u64), 256);
+    }
+
+    #[test]
+    fn removes_row_padding() {
+        // 2 行，每行 3 个字节的数据加 5 个字节的填充
+        let data = [1, 2, 3, 0, 0, 0, 0, 0, 4, 5, 6, 0, 0, 0, 0, 0];
+        assert_eq!(unpad_rows(&data, 8, 3, 2), [1, 2, 3, 4, 5, 6]);
+        // 没有填充时原样返回
+        assert_eq!(unpad_rows(&data, 4, 4, 4), data);
+        // 多出的行被忽略
+        assert_eq!(unpad_rows(&data, 8, 3, 1), [1, 2, 3]);
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// 着色器源码的读取方式
+///
+/// `path` 是相对于着色器根目录的路径，如 `func/common.wgsl`
+pub trait AssetSource: Send + Sync {
+    fn read_to_string(&self, path: &Path) -> std::io::Result<String>;
+
+    /// 读取二进制文件，如 `.spv`
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+        self.read_to_string(path).map(String::into_bytes)
+    }
+
+    /// 文件的最后修改时间，用于热重载；无法获取时返回 `None`
+    fn modified(&self, _path: &Path) -> Option<SystemTime> {
+        None
+    }
+}
+
+/// 默认的读取方式：native 上读取磁盘文件，wasm 上通过 XMLHttpRequest 请求
+#[derive(Clone, Debug)]
+pub struct FsAssetSource {
+    root: PathBuf,
+}
+
+impl FsAssetSource {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        FsAssetSource { root: root.into() }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+}
+
+impl AssetSource for FsAssetSource {
+    #[cfg(not(target_arch = "wasm32"))]
+    fn read_to_string(&self, path: &Path) -> std::io::Result<String> {
+        std::fs::read_to_string(self.root.join(path))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+        std::fs::read(self.root.join(path))
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+        // 同步请求不能设置 arraybuffer 类型的 response，用 x-user-defined 字符集让每个字节对应一个字符
+        let request = web_sys::XmlHttpRequest::new().map_err(js_error)?;
+        request.override_mime_type("text/plain; charset=x-user-defined").map_err(js_error)?;
+        let url = self.root.to_string_lossy().to_string() + "/" + &path.to_string_lossy();
+        request.open_with_async("get", &url, false).map_err(js_error)?;
+        request.send().map_err(js_error)?;
+        match request.status().map_err(js_error)? {
+            200 => {}
+            404 => return Err(Error::new(ErrorKind::NotFound, format!("HTTP status 404: {url}"))),
+            status => return Err(Error::new(ErrorKind::Other, format!("HTTP status {status}: {url}"))),
+        }
+        let text =
+            request.response_text().map_err(js_error)?.ok_or_else(|| Error::new(ErrorKind::Other, "empty response"))?;
+        Ok(text.chars().map(|c| c as u32 as u8).collect())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn modified(&self, path: &Path) -> Option<SystemTime> {
+        std::fs::metadata(self.root.join(path)).and_then(|metadata| metadata.modified()).ok()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn read_to_string(&self, path: &Path) -> std::io::Result<String> {
+        // 主线程中同步的 XMLHttpRequest 已不赞成使用(2021/05/07)
+        let request = web_sys::XmlHttpRequest::new().map_err(js_error)?;
+        request.set_response_type(web_sys::XmlHttpRequestResponseType::None);
+        let url = self.root.to_string_lossy().to_string() + "/" + &path.to_string_lossy();
+        request.open_with_async("get", &url, false).map_err(js_error)?;
+        request.send().map_err(js_error)?;
+        match request.status().map_err(js_error)? {
+            200 => {}
+            404 => return Err(Error::new(ErrorKind::NotFound, format!("HTTP status 404: {url}"))),
+            status => return Err(Error::new(ErrorKind::Other, format!("HTTP status {status}: {url}"))),
+        }
+        request.response_text().map_err(js_error)?.ok_or_else(|| Error::new(ErrorKind::Other, "empty response"))
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn js_error(e: impl std::fmt::Debug) -> Error {
+    Error::new(ErrorKind::Other, format!("{:?}", e))
+}
+
+/// 内嵌到二进制中的着色器源码，不需要访问文件系统
+///
+/// 通常与 build.rs 中的 [`write_embedded_shaders`] 配合使用：
+/// ```ignore
+/// static SHADERS: &[(&str, &str)] = include!(concat!(env!("OUT_DIR"), "/embedded_shaders.rs"));
+/// let preprocessor = ShaderPreprocessor::from_source(EmbeddedAssetSource::new(SHADERS));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct EmbeddedAssetSource {
+    files: HashMap<PathBuf, &'static str>,
+    binaries: HashMap<PathBuf, &'static [u8]>,
+}
+
+impl EmbeddedAssetSource {
+    /// `files` 为 (相对路径, 源码) 列表，路径使用 `/` 分隔
+    pub fn new(files: &[(&'static str, &'static str)]) -> Self {
+        let mut source = EmbeddedAssetSource::default();
+        for (path, code) in files {
+            source.insert(path, code);
+        }
+        source
+    }
+
+    pub fn insert(&mut self, path: &str, code: &'static str) {
+        self.files.insert(PathBuf::from(path), code);
+    }
+
+    /// 二进制文件，如 `include_bytes!("shader.spv")`
+    pub fn insert_bytes(&mut self, path: &str, data: &'static [u8]) {
+        self.binaries.insert(PathBuf::from(path), data);
+    }
+}
+
+impl AssetSource for EmbeddedAssetSource {
+    fn read_to_string(&self, path: &Path) -> std::io::Result<String> {
+        match self.files.get(path) {
+            Some(code) => Ok(code.to_string()),
+            None => Err(Error::new(ErrorKind::NotFound, "not found in embedded shaders")),
+        }
+    }
+
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+        match self.binaries.get(path) {
+            Some(data) => Ok(data.to_vec()),
+            None => self.read_to_string(path).map(String::into_bytes),
+        }
+    }
+}
+
+/// 在 build.rs 中调用：把 `shader_dir` 下的所有 `.wgsl` 及 GLSL 文件生成为 `include_str!` 表，写入 `out_file`
+///
+/// ```ignore
+/// let out_dir = std::env::var("OUT_DIR").unwrap();
+/// idroid::shader::write_embedded_shaders("shader-wgsl", format!("{out_dir}/embedded_shaders.rs")).unwrap();
+/// ```
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write_embedded_shaders(shader_dir: impl AsRef<Path>, out_file: impl AsRef<Path>) -> std::io::Result<()> {
+    let shader_dir = std::fs::canonicalize(shader_dir)?;
+    let mut files: Vec<PathBuf> = vec![];
+    collect_files(&shader_dir, Path::new(""), &mut files)?;
+    files.sort();
+
+    let mut table = String::from("// Generated by idroid::shader::write_embedded_shaders, do not edit.\n&[\n");
+    for file in files.iter() {
+        let full_path = shader_dir.join(file);
+        // build script 约定：文件变化时重新生成
+        println!("cargo:rerun-if-changed={}", full_path.display());
+        let key = file.to_string_lossy().replace('\\', "/");
+        table.push_str(&format!("    ({:?}, include_str!({:?})),\n", key, full_path.to_string_lossy()));
+    }
+    table.push_str("]\n");
+    std::fs::write(out_file, table)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(root.join(dir))? {
+        let entry = entry?;
+        let relative = dir.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            collect_files(root, &relative, files)?;
+        } else if matches!(super::ShaderKind::from_path(&relative), Some(kind) if kind != super::ShaderKind::SpirV) {
+            files.push(relative);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,199 @@
+use super::{validate_glsl, validate_spirv, validate_wgsl, ShaderKind, ShaderPreprocessor};
+use std::path::{Path, PathBuf};
+
+/// [`bundle_shaders`] 的选项，对应 `idroid-shader-bundle` 的命令行参数
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BundleOptions {
+    /// 着色器目录，如 `shader-wgsl`
+    pub input: PathBuf,
+    /// 输出目录，如 `shader-preprocessed-wgsl`
+    pub output: PathBuf,
+    /// 使用 `defines` 完整预处理后校验每个着色器
+    pub validate: bool,
+    pub defines: Vec<(String, String)>,
+    /// 只被 include 的函数库目录，相对于 `input`，不单独输出
+    pub excludes: Vec<PathBuf>,
+}
+
+/// 单个着色器的处理结果
+#[derive(Clone, Debug)]
+pub struct BundledShader {
+    /// 相对于输入目录的路径
+    pub path: PathBuf,
+    /// 输出文件的路径
+    pub output: PathBuf,
+    /// 包含 `#insert_code_segment` 的着色器要到运行时才能确定代码，不做校验
+    pub skipped_validation: bool,
+    /// 预处理、写入或校验失败的原因
+    pub error: Option<String>,
+}
+
+/// 遍历 `input` 下所有 `.wgsl` 及 GLSL 文件，展开 `#include` 后以 [`bundled_file_name`] 的命名写入 `output`，
+/// `.spv` 文件原样复制。条件编译与 `#insert_code_segment` 标记会原样保留，运行时由 `idroid::shader` 再处理一遍。
+///
+/// 只有读取输入目录或创建输出目录失败时返回 Err，单个着色器的错误记录在返回的结果中
+pub fn bundle_shaders(options: &BundleOptions) -> std::io::Result<Vec<BundledShader>> {
+    let mut shaders: Vec<PathBuf> = vec![];
+    collect_shaders(&options.input, Path::new(""), &options.excludes, &mut shaders)?;
+    shaders.sort();
+    std::fs::create_dir_all(&options.output)?;
+
+    let defines: Vec<(&str, &str)> = options.defines.iter().map(|(n, v)| (n.as_str(), v.as_str())).collect();
+    let bundler = ShaderPreprocessor::new(&options.input).with_deferred_directives(true);
+    let validator = ShaderPreprocessor::new(&options.input).with_defines(&defines);
+    let bundled = shaders
+        .into_iter()
+        .map(|path| {
+            let output = options.output.join(bundled_file_name(&path));
+            let mut bundled = BundledShader { path, output, skipped_validation: false, error: None };
+            if let Err(e) = bundle_shader(options, &bundler, &validator, &mut bundled) {
+                bundled.error = Some(e);
+            }
+            bundled
+        })
+        .collect();
+    Ok(bundled)
+}
+
+/// 着色器在输出目录中的文件名，按 `/` → `_` 的规则展开子目录，
+/// 如 `fluid/collide.wgsl` → `fluid_collide.wgsl`，与 iOS 及 wasm 上加载时使用的名称一致
+pub fn bundled_file_name(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/").replace('/', "_")
+}
+
+fn bundle_shader(
+    options: &BundleOptions, bundler: &ShaderPreprocessor, validator: &ShaderPreprocessor, bundled: &mut BundledShader,
+) -> Result<(), String> {
+    let kind = ShaderKind::from_path(&bundled.path);
+    // SPIR-V 不需要预处理，直接复制
+    if kind == Some(ShaderKind::SpirV) {
+        let data = std::fs::read(options.input.join(&bundled.path))
+            .map_err(|e| format!("Unable to read {}: {}", bundled.path.display(), e))?;
+        write_output(&bundled.output, &data)?;
+        if options.validate {
+            validate_spirv(&bundled.path, &data).map_err(|e| e.to_string())?;
+        }
+        return Ok(());
+    }
+
+    let file_name = bundled.path.to_string_lossy().replace('\\', "/");
+    let flattened = bundler.process_file(&file_name).map_err(|e| e.to_string())?;
+    write_output(&bundled.output, flattened.source.as_bytes())?;
+    if options.validate {
+        if flattened.source.contains("#insert_code_segment") {
+            bundled.skipped_validation = true;
+            return Ok(());
+        }
+        let full = validator.process_file(&file_name).map_err(|e| e.to_string())?;
+        match kind {
+            Some(ShaderKind::Glsl(stage)) => validate_glsl(&full, stage),
+            _ => validate_wgsl(&full),
+        }
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn write_output(path: &Path, data: &[u8]) -> Result<(), String> {
+    std::fs::write(path, data).map_err(|e| format!("Unable to write {}: {}", path.display(), e))
+}
+
+// 收集相对于 `root` 的着色器文件路径
+fn collect_shaders(root: &Path, dir: &Path, excludes: &[PathBuf], shaders: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(root.join(dir))? {
+        let entry = entry?;
+        let relative = dir.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            if !excludes.contains(&relative) {
+                collect_shaders(root, &relative, excludes, shaders)?;
+            }
+        } else if ShaderKind::from_path(&relative).is_some() {
+            shaders.push(relative);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 测试结束时删除的临时目录
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str, files: &[(&str, &str)]) -> Self {
+            let root = std::env::temp_dir().join(format!("idroid-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&root);
+            for (path, code) in files {
+                let path = root.join("shader-wgsl").join(path);
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(path, code).unwrap();
+            }
+            TempDir(root)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn names_outputs_by_flattening_directories() {
+        assert_eq!(bundled_file_name(Path::new("collide.wgsl")), "collide.wgsl");
+        assert_eq!(bundled_file_name(Path::new("fluid/lbm/collide.wgsl")), "fluid_lbm_collide.wgsl");
+    }
+
+    #[test]
+    fn bundles_a_shader_directory() {
+        let dir = TempDir::new(
+            "bundle",
+            &[
+                ("func/common.wgsl", "fn helper() -> f32 { return 1.0; }"),
+                (
+                    "fluid/collide.wgsl",
+                    "#include \"func/common.wgsl\"\n@compute @workgroup_size(WORKGROUP_SIZE)\nfn cs_main() {\n\
+                     #ifdef FAST\n    let fast = helper();\n#endif\n}",
+                ),
+                ("segment.wgsl", "#insert_code_segment\n"),
+                ("broken.wgsl", "fn broken( {"),
+                ("notes.txt", "not a shader"),
+            ],
+        );
+        let options = BundleOptions {
+            input: dir.0.join("shader-wgsl"),
+            output: dir.0.join("shader-preprocessed-wgsl"),
+            validate: true,
+            defines: vec![("WORKGROUP_SIZE".to_string(), "8".to_string())],
+            excludes: vec![PathBuf::from("func")],
+        };
+        let bundled = bundle_shaders(&options).unwrap();
+        let paths: Vec<String> = bundled.iter().map(|b| b.path.to_string_lossy().replace('\\', "/")).collect();
+        assert_eq!(paths, ["broken.wgsl", "fluid/collide.wgsl", "segment.wgsl"]);
+
+        assert!(bundled[0].error.is_some());
+        assert!(bundled[0].output.exists());
+
+        // include 被展开，宏与条件编译留到运行时处理
+        assert_eq!(bundled[1].error, None);
+        assert!(!bundled[1].skipped_validation);
+        assert_eq!(bundled[1].output, options.output.join("fluid_collide.wgsl"));
+        let source = std::fs::read_to_string(&bundled[1].output).unwrap();
+        assert!(source.starts_with("fn helper()"), "{source}");
+        assert!(source.contains("@workgroup_size(WORKGROUP_SIZE)"), "{source}");
+        assert!(source.contains("#ifdef FAST\n    let fast = helper();\n#endif"), "{source}");
+
+        assert_eq!(bundled[2].error, None);
+        assert!(bundled[2].skipped_validation);
+        assert_eq!(std::fs::read_to_string(&bundled[2].output).unwrap(), "#insert_code_segment\n");
+    }
+
+    #[test]
+    fn reports_a_missing_input_directory() {
+        let dir = TempDir::new("bundle-missing", &[]);
+        let options = BundleOptions { input: dir.0.join("missing"), output: dir.0.join("out"), ..Default::default() };
+        assert!(bundle_shaders(&options).is_err());
+    }
+}
@@ -0,0 +1,147 @@
+use super::SourceLocation;
+use std::path::PathBuf;
+
+/// 着色器加载错误
+///
+/// `include_chain` 是从顶层着色器文件开始，一直到引发错误的那个文件为止的 `#include` 链，
+/// 不包含出错的文件自身
+#[derive(Debug, Clone)]
+pub enum ShaderError {
+    /// 找不到（或无法读取）着色器文件
+    FileNotFound { path: PathBuf, reason: String, include_chain: Vec<PathBuf> },
+    /// `#include` 形成了循环引用
+    IncludeCycle { path: PathBuf, include_chain: Vec<PathBuf> },
+    /// 预处理指令有误，如 `#endif` 没有对应的 `#ifdef`
+    InvalidDirective { message: String, location: SourceLocation, include_chain: Vec<PathBuf> },
+    /// 传入了代码片段，但着色器中没有对应的 `#insert_code_segment` 标记；不带名称的标记 `name` 为空
+    MissingSegmentMarker { name: String, include_chain: Vec<PathBuf> },
+    /// 着色器中有 `#insert_code_segment` 标记，但没有提供对应的代码片段
+    MissingSegmentCode { name: String, location: SourceLocation, include_chain: Vec<PathBuf> },
+    /// 预处理后的 WGSL 没能通过 naga 的解析或校验
+    InvalidWgsl { message: String, location: Option<SourceLocation>, include_chain: Vec<PathBuf> },
+    /// 预处理后的 GLSL 没能通过 naga 的解析或校验
+    InvalidGlsl { message: String, location: Option<SourceLocation>, include_chain: Vec<PathBuf> },
+    /// SPIR-V 文件没能通过 naga 的解析或校验
+    InvalidSpirV { path: PathBuf, message: String },
+    /// 无法由扩展名判断着色器语言
+    UnknownShaderKind { path: PathBuf },
+    /// Rust 端提供的绑定资源与着色器中的声明不一致
+    BindingMismatch {
+        group: u32,
+        binding: u32,
+        message: String,
+        location: Option<SourceLocation>,
+        include_chain: Vec<PathBuf>,
+    },
+    /// Rust 结构体与着色器中结构体的内存布局不一致，或无法生成对应的 Rust 结构体
+    LayoutMismatch {
+        struct_name: String,
+        message: String,
+        location: Option<SourceLocation>,
+        include_chain: Vec<PathBuf>,
+    },
+    /// 按名称或序号提供的资源，在着色器的 bind group 中找不到对应的声明
+    UnknownBinding { group: u32, slot: String },
+    /// 对应的着色器前端没有编译进来，`feature` 为需要开启的 cargo feature
+    MissingFeature { language: &'static str, feature: &'static str },
+}
+
+impl ShaderError {
+    pub fn include_chain(&self) -> &[PathBuf] {
+        match self {
+            ShaderError::FileNotFound { include_chain, .. }
+            | ShaderError::IncludeCycle { include_chain, .. }
+            | ShaderError::InvalidDirective { include_chain, .. }
+            | ShaderError::MissingSegmentMarker { include_chain, .. }
+            | ShaderError::MissingSegmentCode { include_chain, .. }
+            | ShaderError::InvalidWgsl { include_chain, .. }
+            | ShaderError::InvalidGlsl { include_chain, .. }
+            | ShaderError::BindingMismatch { include_chain, .. }
+            | ShaderError::LayoutMismatch { include_chain, .. } => include_chain,
+            ShaderError::InvalidSpirV { .. }
+            | ShaderError::UnknownShaderKind { .. }
+            | ShaderError::UnknownBinding { .. }
+            | ShaderError::MissingFeature { .. } => &[],
+        }
+    }
+}
+
+impl std::fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShaderError::FileNotFound { path, reason, .. } => {
+                write!(f, "Unable to read {}: {}", path.display(), reason)?
+            }
+            ShaderError::IncludeCycle { path, .. } => write!(f, "Include cycle detected at {}", path.display())?,
+            ShaderError::InvalidDirective { message, location, .. } => {
+                write!(f, "Invalid preprocessor directive at {location}: {message}")?
+            }
+            ShaderError::MissingSegmentMarker { name, .. } => write!(
+                f,
+                "Code segment `{}` was provided but no matching `#insert_code_segment` marker was found",
+                segment_display_name(name)
+            )?,
+            ShaderError::MissingSegmentCode { name, location, .. } => {
+                write!(f, "No code was provided for segment `{}` required at {location}", segment_display_name(name))?
+            }
+            ShaderError::InvalidWgsl { message, location, .. } => {
+                if let Some(location) = location {
+                    write!(f, "Invalid WGSL at {location}: {message}")?
+                } else {
+                    write!(f, "Invalid WGSL: {message}")?
+                }
+            }
+            ShaderError::InvalidGlsl { message, location, .. } => {
+                if let Some(location) = location {
+                    write!(f, "Invalid GLSL at {location}: {message}")?
+                } else {
+                    write!(f, "Invalid GLSL: {message}")?
+                }
+            }
+            ShaderError::InvalidSpirV { path, message } => {
+                write!(f, "Invalid SPIR-V in {}: {}", path.display(), message)?
+            }
+            ShaderError::UnknownShaderKind { path } => write!(
+                f,
+                "Unable to determine the shader language of {}, expected .wgsl, .vert, .frag, .comp or .spv",
+                path.display()
+            )?,
+            ShaderError::BindingMismatch { group, binding, message, location, .. } => {
+                write!(f, "Binding mismatch at @group({group}) @binding({binding})")?;
+                if let Some(location) = location {
+                    write!(f, " declared at {location}")?;
+                }
+                write!(f, ": {message}")?
+            }
+            ShaderError::LayoutMismatch { struct_name, message, location, .. } => {
+                write!(f, "Layout mismatch for struct `{struct_name}`")?;
+                if let Some(location) = location {
+                    write!(f, " declared at {location}")?;
+                }
+                write!(f, ": {message}")?
+            }
+            ShaderError::UnknownBinding { group, slot } => {
+                write!(f, "No binding {slot} is declared in @group({group}) of the shader")?
+            }
+            ShaderError::MissingFeature { language, feature } => {
+                write!(f, "Loading {language} shaders requires the `{feature}` feature of idroid")?
+            }
+        }
+        let include_chain = self.include_chain();
+        if !include_chain.is_empty() {
+            let chain: Vec<String> = include_chain.iter().map(|p| p.display().to_string()).collect();
+            write!(f, " (include chain: {})", chain.join(" -> "))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
+fn segment_display_name(name: &str) -> &str {
+    if name.is_empty() {
+        "<anonymous>"
+    } else {
+        name
+    }
+}
@@ -0,0 +1,76 @@
+use super::{PreprocessedShader, ShaderError};
+use std::path::Path;
+
+/// 由文件扩展名决定使用 naga 的哪个前端
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShaderKind {
+    Wgsl,
+    /// `.vert` / `.frag` / `.comp`，一个文件只包含一个阶段
+    Glsl(naga::ShaderStage),
+    /// `.spv`，二进制文件，不经过预处理
+    SpirV,
+}
+
+impl ShaderKind {
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        match path.as_ref().extension()?.to_str()? {
+            "wgsl" => Some(ShaderKind::Wgsl),
+            "vert" => Some(ShaderKind::Glsl(naga::ShaderStage::Vertex)),
+            "frag" => Some(ShaderKind::Glsl(naga::ShaderStage::Fragment)),
+            "comp" => Some(ShaderKind::Glsl(naga::ShaderStage::Compute)),
+            "spv" => Some(ShaderKind::SpirV),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "glsl")]
+pub(super) fn parse_glsl(
+    shader: &PreprocessedShader, stage: naga::ShaderStage,
+) -> Result<(naga::Module, naga::valid::ModuleInfo), ShaderError> {
+    // 不带参数的宏已经在预处理时展开，这里不需要再传入；带参数的宏保留在源码中，由 naga 展开
+    let options = naga::front::glsl::Options { stage, defines: naga::FastHashMap::default() };
+    let module = naga::front::glsl::Parser::default().parse(&options, &shader.source).map_err(|errors| {
+        let line = errors
+            .first()
+            .filter(|e| e.meta.is_defined())
+            .map(|e| e.meta.location(&shader.source).line_number as usize);
+        let messages: Vec<String> = errors.iter().map(|e| e.kind.to_string()).collect();
+        invalid_glsl(shader, messages.join("; "), line)
+    })?;
+    let info = super::validate_module(&module, Some(&shader.source))
+        .map_err(|(message, line)| invalid_glsl(shader, message, line))?;
+    Ok((module, info))
+}
+
+#[cfg(not(feature = "glsl"))]
+pub(super) fn parse_glsl(
+    _shader: &PreprocessedShader, _stage: naga::ShaderStage,
+) -> Result<(naga::Module, naga::valid::ModuleInfo), ShaderError> {
+    Err(ShaderError::MissingFeature { language: "GLSL", feature: "glsl" })
+}
+
+#[cfg(feature = "spirv")]
+pub(super) fn parse_spirv(path: &Path, data: &[u8]) -> Result<(naga::Module, naga::valid::ModuleInfo), ShaderError> {
+    // 与 wgpu 内部解析 SPIR-V 时使用的选项一致
+    let options = naga::front::spv::Options {
+        adjust_coordinate_space: false,
+        strict_capabilities: true,
+        block_ctx_dump_prefix: None,
+    };
+    let invalid_spirv = |message: String| ShaderError::InvalidSpirV { path: path.to_path_buf(), message };
+    let module = naga::front::spv::parse_u8_slice(data, &options).map_err(|e| invalid_spirv(e.to_string()))?;
+    let info = super::validate_module(&module, None).map_err(|(message, _)| invalid_spirv(message))?;
+    Ok((module, info))
+}
+
+#[cfg(not(feature = "spirv"))]
+pub(super) fn parse_spirv(_path: &Path, _data: &[u8]) -> Result<(naga::Module, naga::valid::ModuleInfo), ShaderError> {
+    Err(ShaderError::MissingFeature { language: "SPIR-V", feature: "spirv" })
+}
+
+#[cfg(feature = "glsl")]
+fn invalid_glsl(shader: &PreprocessedShader, message: String, line: Option<usize>) -> ShaderError {
+    let (location, include_chain) = shader.source_map.locate(line);
+    ShaderError::InvalidGlsl { message, location, include_chain }
+}
@@ -0,0 +1,438 @@
+use super::{ShaderError, ShaderReflection, SourceLocation};
+
+/// Rust 结构体中一个字段的内存布局
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FieldLayout {
+    pub name: &'static str,
+    pub offset: usize,
+    pub size: usize,
+}
+
+/// 用于与着色器中的结构体比对内存布局，通常由 [`impl_uniform_layout!`](crate::impl_uniform_layout) 实现
+pub trait UniformLayout: Sized {
+    fn fields() -> Vec<FieldLayout>;
+
+    fn size() -> usize {
+        std::mem::size_of::<Self>()
+    }
+}
+
+/// 为 `#[repr(C)]` 结构体实现 [`UniformLayout`]，需要列出与 WGSL 结构体成员同名的字段，
+/// 填充字段可以省略：
+/// ```ignore
+/// idroid::impl_uniform_layout!(MVPUniform2 { p_matrix, mv_matrix });
+/// reflection.check_struct_layout::<MVPUniform2>("MVPUniform2")?;
+/// ```
+#[macro_export]
+macro_rules! impl_uniform_layout {
+    ($ty:ty { $($field:ident),* $(,)? }) => {
+        impl $crate::shader::UniformLayout for $ty {
+            fn fields() -> Vec<$crate::shader::FieldLayout> {
+                let uninit = ::std::mem::MaybeUninit::<$ty>::uninit();
+                let base = uninit.as_ptr();
+                vec![$({
+                    // 只计算字段的地址，不会读取未初始化的内存
+                    let field = unsafe { ::std::ptr::addr_of!((*base).$field) };
+                    $crate::shader::FieldLayout {
+                        name: stringify!($field),
+                        offset: field as usize - base as usize,
+                        size: $crate::shader::size_of_pointee(field),
+                    }
+                }),*]
+            }
+        }
+    };
+}
+
+#[doc(hidden)]
+pub fn size_of_pointee<T>(_: *const T) -> usize {
+    std::mem::size_of::<T>()
+}
+
+/// 着色器中声明的结构体，偏移与大小遵循 WGSL 的内存布局规则
+#[derive(Clone, Debug)]
+pub struct StructReflection {
+    pub name: String,
+    pub size: u32,
+    pub members: Vec<StructMemberReflection>,
+    pub location: Option<SourceLocation>,
+    // 在预处理输出中的行号
+    pub(crate) line: Option<usize>,
+}
+
+#[derive(Clone, Debug)]
+pub struct StructMemberReflection {
+    pub name: String,
+    pub offset: u32,
+    /// 运行时大小的数组为 0
+    pub size: u32,
+}
+
+#[allow(dead_code)]
+impl ShaderReflection {
+    pub fn struct_layout(&self, name: &str) -> Option<&StructReflection> {
+        self.structs.iter().find(|s| s.name == name)
+    }
+
+    /// 比对 Rust 类型 `T` 与着色器中名为 `struct_name` 的结构体的大小及各成员的偏移。
+    /// 按名称对应，Rust 中多出的字段（如填充字段）会被忽略
+    pub fn check_struct_layout<T: UniformLayout>(&self, struct_name: &str) -> Result<(), ShaderError> {
+        let wgsl = self.struct_layout(struct_name).ok_or_else(|| ShaderError::LayoutMismatch {
+            struct_name: struct_name.to_string(),
+            message: "no such struct is declared in the shader".to_string(),
+            location: None,
+            include_chain: vec![],
+        })?;
+        let fields = T::fields();
+        for member in wgsl.members.iter() {
+            let field = match fields.iter().find(|f| f.name == member.name) {
+                Some(field) => field,
+                None => return Err(self.layout_mismatch(wgsl, format!("the Rust type has no field `{}`", member.name))),
+            };
+            if field.offset != member.offset as usize {
+                return Err(self.layout_mismatch(
+                    wgsl,
+                    format!(
+                        "field `{}` is at offset {} in Rust, but at offset {} in WGSL (missing padding?)",
+                        member.name, field.offset, member.offset
+                    ),
+                ));
+            }
+            if member.size > 0 && field.size != member.size as usize {
+                return Err(self.layout_mismatch(
+                    wgsl,
+                    format!(
+                        "field `{}` is {} bytes in Rust, but {} bytes in WGSL",
+                        member.name, field.size, member.size
+                    ),
+                ));
+            }
+        }
+        // 以运行时大小的数组结尾时，Rust 类型只对应固定大小的部分
+        let has_runtime_array = wgsl.members.last().map_or(false, |m| m.size == 0);
+        if !has_runtime_array && T::size() != wgsl.size as usize {
+            return Err(self.layout_mismatch(
+                wgsl,
+                format!("size is {} bytes in Rust, but {} bytes in WGSL", T::size(), wgsl.size),
+            ));
+        }
+        Ok(())
+    }
+
+    /// 由着色器中的结构体生成填充好的 `#[repr(C)]` Rust 结构体，被引用到的结构体会一起生成
+    pub fn generate_rust_struct(&self, struct_name: &str) -> Result<String, ShaderError> {
+        let mut generated: Vec<naga::Handle<naga::Type>> = vec![];
+        let handle = self
+            .module
+            .types
+            .iter()
+            .find(|(_, ty)| {
+                ty.name.as_deref() == Some(struct_name) && matches!(ty.inner, naga::TypeInner::Struct { .. })
+            })
+            .map(|(handle, _)| handle)
+            .ok_or_else(|| ShaderError::LayoutMismatch {
+                struct_name: struct_name.to_string(),
+                message: "no such struct is declared in the shader".to_string(),
+                location: None,
+                include_chain: vec![],
+            })?;
+        let mut code = String::new();
+        self.write_rust_struct(handle, &mut generated, &mut code)?;
+        Ok(code)
+    }
+
+    fn write_rust_struct(
+        &self, handle: naga::Handle<naga::Type>, generated: &mut Vec<naga::Handle<naga::Type>>, code: &mut String,
+    ) -> Result<(), ShaderError> {
+        if generated.contains(&handle) {
+            return Ok(());
+        }
+        generated.push(handle);
+        let ty = &self.module.types[handle];
+        let name = ty.name.clone().unwrap_or_default();
+        let (members, span) = match &ty.inner {
+            naga::TypeInner::Struct { members, span } => (members, *span),
+            _ => return Ok(()),
+        };
+        let unsupported = |message: String| ShaderError::LayoutMismatch {
+            struct_name: name.clone(),
+            message,
+            location: None,
+            include_chain: vec![],
+        };
+
+        // 先生成被引用的结构体
+        for member in members.iter() {
+            let mut base = member.ty;
+            while let naga::TypeInner::Array { base: element, .. } = self.module.types[base].inner {
+                base = element;
+            }
+            self.write_rust_struct(base, generated, code)?;
+        }
+
+        let mut fields = String::new();
+        let mut offset = 0;
+        let mut pad_index = 0;
+        for member in members.iter() {
+            let member_name = member.name.clone().unwrap_or_default();
+            if member.offset > offset {
+                fields.push_str(&format!("    pub _pad{}: [u32; {}],\n", pad_index, (member.offset - offset) / 4));
+                pad_index += 1;
+            }
+            match self.rust_type(member.ty) {
+                Some((rust_type, size)) => {
+                    fields.push_str(&format!("    pub {member_name}: {rust_type},\n"));
+                    offset = member.offset + size;
+                }
+                None => {
+                    // 运行时大小的数组无法放入 Pod 结构体，需要单独的 buffer 切片
+                    if let naga::TypeInner::Array { size: naga::ArraySize::Dynamic, .. } =
+                        self.module.types[member.ty].inner
+                    {
+                        fields.push_str(&format!("    // {member_name}: runtime-sized array, not included\n"));
+                        offset = span.max(member.offset);
+                        break;
+                    } else {
+                        return Err(unsupported(format!(
+                            "member `{member_name}` has a type without a Rust equivalent"
+                        )));
+                    }
+                }
+            }
+        }
+        if span > offset {
+            fields.push_str(&format!("    pub _pad{}: [u32; {}],\n", pad_index, (span - offset) / 4));
+        }
+
+        let field_names: Vec<String> = members
+            .iter()
+            .filter(|m| self.rust_type(m.ty).is_some())
+            .map(|m| m.name.clone().unwrap_or_default())
+            .collect();
+        code.push_str(&format!(
+            "#[repr(C)]\n#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]\npub struct {name} {{\n{fields}}}\n\
+             idroid::impl_uniform_layout!({name} {{ {} }});\n\n",
+            field_names.join(", ")
+        ));
+        Ok(())
+    }
+
+    // 返回 Rust 类型及其大小，运行时大小的数组等没有对应类型时返回 None
+    fn rust_type(&self, handle: naga::Handle<naga::Type>) -> Option<(String, u32)> {
+        let ty = &self.module.types[handle];
+        match ty.inner {
+            naga::TypeInner::Scalar { kind, width } | naga::TypeInner::Atomic { kind, width } => {
+                Some((scalar_type(kind, width)?.to_string(), width as u32))
+            }
+            naga::TypeInner::Vector { size, kind, width } => {
+                Some((format!("[{}; {}]", scalar_type(kind, width)?, size as u32), size as u32 * width as u32))
+            }
+            naga::TypeInner::Matrix { columns, rows, width } => {
+                // 每一列按 vec2 或 vec4 对齐，mat3 的每一列需要补齐到 4 个分量
+                let column_len = if rows == naga::VectorSize::Tri { 4 } else { rows as u32 };
+                let scalar = scalar_type(naga::ScalarKind::Float, width)?;
+                Some((
+                    format!("[[{}; {}]; {}]", scalar, column_len, columns as u32),
+                    column_len * columns as u32 * width as u32,
+                ))
+            }
+            naga::TypeInner::Array { base, size: naga::ArraySize::Constant(c), stride } => {
+                let count = match self.module.constants[c].inner {
+                    naga::ConstantInner::Scalar { value: naga::ScalarValue::Uint(v), .. } => v as u32,
+                    naga::ConstantInner::Scalar { value: naga::ScalarValue::Sint(v), .. } => v as u32,
+                    _ => return None,
+                };
+                let (element, element_size) = self.rust_type(base)?;
+                let element = if element_size == stride {
+                    element
+                } else {
+                    // 如 array<vec3<f32>, N> 的 stride 为 16，元素补齐到 stride
+                    match self.module.types[base].inner {
+                        naga::TypeInner::Scalar { kind, width } | naga::TypeInner::Vector { kind, width, .. } => {
+                            format!("[{}; {}]", scalar_type(kind, width)?, stride / width as u32)
+                        }
+                        _ => return None,
+                    }
+                };
+                Some((format!("[{element}; {count}]"), stride * count))
+            }
+            naga::TypeInner::Struct { span, .. } => Some((ty.name.clone()?, span)),
+            _ => None,
+        }
+    }
+
+    fn layout_mismatch(&self, wgsl: &StructReflection, message: String) -> ShaderError {
+        let (location, include_chain) = self.source_map.locate(wgsl.line);
+        ShaderError::LayoutMismatch { struct_name: wgsl.name.clone(), message, location, include_chain }
+    }
+}
+
+fn scalar_type(kind: naga::ScalarKind, width: naga::Bytes) -> Option<&'static str> {
+    match (kind, width) {
+        (naga::ScalarKind::Float, 4) => Some("f32"),
+        (naga::ScalarKind::Float, 8) => Some("f64"),
+        (naga::ScalarKind::Sint, 4) => Some("i32"),
+        (naga::ScalarKind::Uint, 4) => Some("u32"),
+        _ => None,
+    }
+}
+
+pub(super) fn reflect_structs(module: &naga::Module, shader: &super::PreprocessedShader) -> Vec<StructReflection> {
+    let mut structs = vec![];
+    for (handle, ty) in module.types.iter() {
+        let (members, span) = match &ty.inner {
+            naga::TypeInner::Struct { members, span } => (members, *span),
+            _ => continue,
+        };
+        let name = match &ty.name {
+            Some(name) => name.clone(),
+            None => continue,
+        };
+        let members = members
+            .iter()
+            .map(|m| {
+                let size = match module.types[m.ty].inner {
+                    naga::TypeInner::Array { size: naga::ArraySize::Dynamic, .. } => 0,
+                    ref inner => inner.size(&module.constants),
+                };
+                StructMemberReflection { name: m.name.clone().unwrap_or_default(), offset: m.offset, size }
+            })
+            .collect();
+        let type_span = module.types.get_span(handle);
+        let line =
+            if type_span.is_defined() { Some(type_span.location(&shader.source).line_number as usize) } else { None };
+        let (location, _) = shader.source_map.locate(line);
+        structs.push(StructReflection { name, size: span, members, location, line });
+    }
+    structs
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shader::{EmbeddedAssetSource, ShaderError, ShaderPreprocessor, ShaderReflection};
+
+    const SHADER: &str = "struct Params {\n    color: vec3<f32>,\n    scale: f32,\n    offset: vec2<f32>,\n};\n\
+                          @group(0) @binding(0) var<uniform> params: Params;\n\
+                          @compute @workgroup_size(1)\nfn cs_main() { let s = params.scale; }\n";
+
+    #[repr(C)]
+    struct Params {
+        color: [f32; 3],
+        scale: f32,
+        offset: [f32; 2],
+        _pad: [f32; 2],
+    }
+    crate::impl_uniform_layout!(Params { color, scale, offset });
+
+    // vec3 后缺少填充，scale 的偏移及结构体大小都与 WGSL 不一致
+    #[repr(C)]
+    struct Unpadded {
+        color: [f32; 3],
+        offset: [f32; 2],
+        scale: f32,
+    }
+    crate::impl_uniform_layout!(Unpadded { color, scale, offset });
+
+    // vec3 后接结构体、结构体后接 vec3 都需要填充，结构体大小还要补齐到 16 字节的对齐
+    const SCENE_SHADER: &str = "struct Light {\n    position: vec3<f32>,\n    intensity: f32,\n};\n\
+                                struct Scene {\n    ambient: vec3<f32>,\n    light: Light,\n    exposure: f32,\n\
+                                tint: vec3<f32>,\n    gamma: f32,\n    count: u32,\n};\n\
+                                @group(0) @binding(0) var<uniform> scene: Scene;\n\
+                                @compute @workgroup_size(1)\nfn cs_main() { let e = scene.exposure; }\n";
+
+    const GENERATED_SCENE: &str = "#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Light {
+    pub position: [f32; 3],
+    pub intensity: f32,
+}
+idroid::impl_uniform_layout!(Light { position, intensity });
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Scene {
+    pub ambient: [f32; 3],
+    pub _pad0: [u32; 1],
+    pub light: Light,
+    pub exposure: f32,
+    pub _pad1: [u32; 3],
+    pub tint: [f32; 3],
+    pub gamma: f32,
+    pub count: u32,
+    pub _pad2: [u32; 3],
+}
+idroid::impl_uniform_layout!(Scene { ambient, light, exposure, tint, gamma, count });
+
+";
+
+    // 与 GENERATED_SCENE 相同的代码，Pod 的派生同时保证了没有隐式填充
+    #[repr(C)]
+    #[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+    pub struct Light {
+        pub position: [f32; 3],
+        pub intensity: f32,
+    }
+    crate::impl_uniform_layout!(Light { position, intensity });
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+    pub struct Scene {
+        pub ambient: [f32; 3],
+        pub _pad0: [u32; 1],
+        pub light: Light,
+        pub exposure: f32,
+        pub _pad1: [u32; 3],
+        pub tint: [f32; 3],
+        pub gamma: f32,
+        pub count: u32,
+        pub _pad2: [u32; 3],
+    }
+    crate::impl_uniform_layout!(Scene { ambient, light, exposure, tint, gamma, count });
+
+    fn reflect_source(file_name: &'static str, source: &'static str) -> ShaderReflection {
+        let shader = ShaderPreprocessor::from_source(EmbeddedAssetSource::new(&[(file_name, source)]))
+            .process(file_name.trim_end_matches(".wgsl"))
+            .unwrap();
+        crate::shader::reflect(&shader).unwrap()
+    }
+
+    fn reflection() -> ShaderReflection {
+        reflect_source("params.wgsl", SHADER)
+    }
+
+    #[test]
+    fn accepts_matching_layout() {
+        let reflection = reflection();
+        let layout = reflection.struct_layout("Params").unwrap();
+        assert_eq!(layout.size, 32);
+        assert_eq!(layout.members.iter().map(|m| m.offset).collect::<Vec<_>>(), [0, 12, 16]);
+        reflection.check_struct_layout::<Params>("Params").unwrap();
+    }
+
+    #[test]
+    fn reports_layout_mismatch() {
+        let reflection = reflection();
+        match reflection.check_struct_layout::<Unpadded>("Params") {
+            Err(ShaderError::LayoutMismatch { struct_name, message, location, .. }) => {
+                assert_eq!(struct_name, "Params");
+                assert!(message.contains("`scale`"), "{message}");
+                assert_eq!(location.unwrap().line, 1);
+            }
+            other => panic!("expected a layout mismatch, got {:?}", other),
+        }
+        assert!(matches!(reflection.check_struct_layout::<Params>("Missing"), Err(ShaderError::LayoutMismatch { .. })));
+    }
+
+    #[test]
+    fn generates_padded_rust_structs() {
+        let reflection = reflect_source("scene.wgsl", SCENE_SHADER);
+        let scene = reflection.struct_layout("Scene").unwrap();
+        assert_eq!(scene.size, 80);
+        assert_eq!(scene.members.iter().map(|m| m.offset).collect::<Vec<_>>(), [0, 16, 32, 48, 60, 64]);
+
+        assert_eq!(reflection.generate_rust_struct("Scene").unwrap(), GENERATED_SCENE);
+        reflection.check_struct_layout::<Light>("Light").unwrap();
+        reflection.check_struct_layout::<Scene>("Scene").unwrap();
+        assert!(matches!(reflection.generate_rust_struct("Missing"), Err(ShaderError::LayoutMismatch { .. })));
+    }
+}
@@ -0,0 +1,266 @@
+use super::{PreprocessedShader, ShaderError, ShaderPreprocessor};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use wgpu::ShaderModule;
+
+/// 缓存的键，`defines` 与 `segments` 按名称排序，与传入的顺序无关
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ShaderKey {
+    pub shader_name: String,
+    pub defines: Vec<(String, String)>,
+    pub segments: Vec<(String, String)>,
+}
+
+impl ShaderKey {
+    pub fn new(shader_name: &str, defines: &[(&str, &str)], segments: &[(&str, &str)]) -> Self {
+        ShaderKey {
+            shader_name: shader_name.to_string(),
+            defines: sorted_pairs(defines),
+            segments: sorted_pairs(segments),
+        }
+    }
+}
+
+fn sorted_pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    let mut pairs: Vec<(String, String)> = pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+    pairs.sort();
+    pairs
+}
+
+/// 缓存命中统计
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ShaderLibraryStats {
+    pub source_hits: usize,
+    pub source_misses: usize,
+    pub module_hits: usize,
+    pub module_misses: usize,
+}
+
+/// 按 `(shader_name, defines, segments)` 缓存预处理结果及创建好的 `ShaderModule`
+///
+/// 多个视图使用同一个着色器时，只读取、预处理并编译一次。`ShaderModule` 与 `device` 绑定，
+/// 一个 `ShaderLibrary` 只能用于同一个 `device`
+pub struct ShaderLibrary {
+    preprocessor: ShaderPreprocessor,
+    // 使用 `create_shader_module` 相同的默认目录时，iOS 与 wasm 上需要转换着色器名称
+    platform_names: bool,
+    sources: HashMap<ShaderKey, Arc<PreprocessedShader>>,
+    modules: HashMap<ShaderKey, Arc<ShaderModule>>,
+    stats: ShaderLibraryStats,
+}
+
+#[allow(dead_code)]
+impl ShaderLibrary {
+    /// 使用与 `create_shader_module` 相同的着色器目录
+    pub fn new() -> Self {
+        ShaderLibrary { platform_names: true, ..Self::with_preprocessor(super::default_preprocessor()) }
+    }
+
+    /// `preprocessor` 上设置的 defines 与代码片段对所有着色器生效
+    pub fn with_preprocessor(preprocessor: ShaderPreprocessor) -> Self {
+        ShaderLibrary {
+            preprocessor,
+            platform_names: false,
+            sources: HashMap::new(),
+            modules: HashMap::new(),
+            stats: ShaderLibraryStats::default(),
+        }
+    }
+
+    /// 与 `create_shader_module` 相同，但结果会被缓存
+    pub fn create_shader_module(
+        &mut self, device: &wgpu::Device, shader_name: &str, label: Option<&str>,
+    ) -> Result<Arc<ShaderModule>, ShaderError> {
+        self.get_or_create(device, shader_name, &[], &[], label)
+    }
+
+    /// 与 `insert_code_then_create` 相同，但结果会被缓存
+    pub fn insert_code_then_create(
+        &mut self, device: &wgpu::Device, shader_name: &str, code_segment: Option<&str>, label: Option<&str>,
+    ) -> Result<Arc<ShaderModule>, ShaderError> {
+        match code_segment {
+            Some(segment) => self.get_or_create(device, shader_name, &[], &[("", segment)], label),
+            None => self.get_or_create(device, shader_name, &[], &[], label),
+        }
+    }
+
+    /// 缓存中没有时预处理并创建 module；`label` 只在第一次创建时使用
+    pub fn get_or_create(
+        &mut self, device: &wgpu::Device, shader_name: &str, defines: &[(&str, &str)], segments: &[(&str, &str)],
+        label: Option<&str>,
+    ) -> Result<Arc<ShaderModule>, ShaderError> {
+        let key = ShaderKey::new(shader_name, defines, segments);
+        if let Some(module) = self.modules.get(&key) {
+            self.stats.module_hits += 1;
+            return Ok(module.clone());
+        }
+        let shader = self.preprocess(shader_name, defines, segments)?;
+        let module = Arc::new(super::create_shader_module_from_preprocessed(device, &shader, label)?);
+        self.stats.module_misses += 1;
+        self.modules.insert(key, module.clone());
+        Ok(module)
+    }
+
+    /// 只做预处理，不创建 module，如用于反射绑定信息
+    pub fn preprocess(
+        &mut self, shader_name: &str, defines: &[(&str, &str)], segments: &[(&str, &str)],
+    ) -> Result<Arc<PreprocessedShader>, ShaderError> {
+        let key = ShaderKey::new(shader_name, defines, segments);
+        if let Some(shader) = self.sources.get(&key) {
+            self.stats.source_hits += 1;
+            return Ok(shader.clone());
+        }
+        let name = if self.platform_names { super::platform_shader_name(shader_name) } else { shader_name.to_string() };
+        let preprocessor = self.preprocessor.clone().with_defines(defines).with_segments(segments);
+        let shader = Arc::new(preprocessor.process(&name)?);
+        self.stats.source_misses += 1;
+        self.sources.insert(key, shader.clone());
+        Ok(shader)
+    }
+
+    pub fn stats(&self) -> ShaderLibraryStats {
+        self.stats
+    }
+
+    /// 已缓存的 module 个数
+    pub fn len(&self) -> usize {
+        self.modules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty()
+    }
+
+    pub fn contains(&self, shader_name: &str, defines: &[(&str, &str)], segments: &[(&str, &str)]) -> bool {
+        self.modules.contains_key(&ShaderKey::new(shader_name, defines, segments))
+    }
+
+    /// 移除 `shader_name` 的所有变体，返回移除的 module 个数。
+    /// 已经取出的 `Arc<ShaderModule>` 不受影响
+    pub fn invalidate(&mut self, shader_name: &str) -> usize {
+        self.sources.retain(|key, _| key.shader_name != shader_name);
+        let count = self.modules.len();
+        self.modules.retain(|key, _| key.shader_name != shader_name);
+        count - self.modules.len()
+    }
+
+    /// 移除所有直接或间接 `#include` 了 `file` 的缓存，`file` 是相对于着色器根目录的路径，
+    /// 如 `func/common.wgsl`；返回移除的 module 个数
+    pub fn invalidate_file(&mut self, file: impl AsRef<Path>) -> usize {
+        let file = file.as_ref();
+        let stale: Vec<ShaderKey> = self
+            .sources
+            .iter()
+            .filter(|(_, shader)| shader.source_map.files().any(|f| f == file))
+            .map(|(key, _)| key.clone())
+            .collect();
+        let mut count = 0;
+        for key in stale.iter() {
+            self.sources.remove(key);
+            if self.modules.remove(key).is_some() {
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// 清空缓存，统计数据保留
+    pub fn clear(&mut self) {
+        self.sources.clear();
+        self.modules.clear();
+    }
+}
+
+impl Default for ShaderLibrary {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shader::EmbeddedAssetSource;
+
+    fn library() -> ShaderLibrary {
+        ShaderLibrary::with_preprocessor(ShaderPreprocessor::from_source(EmbeddedAssetSource::new(&[
+            ("a.wgsl", "#include \"func/common.wgsl\"\n#ifdef FAST\nfast\n#endif\n#insert_code_segment"),
+            ("b.wgsl", "b"),
+            ("func/common.wgsl", "common"),
+        ])))
+    }
+
+    fn stats(source_hits: usize, source_misses: usize) -> ShaderLibraryStats {
+        ShaderLibraryStats { source_hits, source_misses, ..Default::default() }
+    }
+
+    #[test]
+    fn sorts_defines_and_segments_in_keys() {
+        let key = ShaderKey::new("a", &[("B", "1"), ("A", "2")], &[("y", "y"), ("x", "x")]);
+        assert_eq!(key, ShaderKey::new("a", &[("A", "2"), ("B", "1")], &[("x", "x"), ("y", "y")]));
+        assert_ne!(key, ShaderKey::new("a", &[("A", "1"), ("B", "2")], &[("x", "x"), ("y", "y")]));
+        assert_ne!(key, ShaderKey::new("b", &[("A", "2"), ("B", "1")], &[("x", "x"), ("y", "y")]));
+    }
+
+    #[test]
+    fn counts_cache_hits_and_misses() {
+        let mut library = library();
+        let segment = [("", "segment")];
+        let first = library.preprocess("a", &[("FAST", "1"), ("N", "2")], &segment).unwrap();
+        assert!(first.source.contains("fast"));
+        let second = library.preprocess("a", &[("N", "2"), ("FAST", "1")], &segment).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(library.stats(), stats(1, 1));
+
+        // defines 或代码片段不同时是另一个变体
+        let slow = library.preprocess("a", &[("N", "2")], &segment).unwrap();
+        assert!(!slow.source.contains("fast"));
+        library.preprocess("a", &[("N", "2")], &[("", "other")]).unwrap();
+        assert_eq!(library.stats(), stats(1, 3));
+
+        // 出错的结果不会被缓存
+        assert!(library.preprocess("missing", &[], &[]).is_err());
+        assert!(library.preprocess("missing", &[], &[]).is_err());
+        assert_eq!(library.stats(), stats(1, 3));
+    }
+
+    #[test]
+    fn invalidates_shaders_including_a_file() {
+        let mut library = library();
+        library.preprocess("a", &[], &[("", "x")]).unwrap();
+        library.preprocess("a", &[("FAST", "1")], &[("", "x")]).unwrap();
+        library.preprocess("b", &[], &[]).unwrap();
+
+        library.invalidate_file("func/common.wgsl");
+        library.preprocess("b", &[], &[]).unwrap();
+        assert_eq!(library.stats(), stats(1, 3));
+        library.preprocess("a", &[], &[("", "x")]).unwrap();
+        library.preprocess("a", &[("FAST", "1")], &[("", "x")]).unwrap();
+        assert_eq!(library.stats(), stats(1, 5));
+
+        library.invalidate("b");
+        library.preprocess("b", &[], &[]).unwrap();
+        library.preprocess("a", &[], &[("", "x")]).unwrap();
+        assert_eq!(library.stats(), stats(2, 6));
+    }
+
+    #[test]
+    #[ignore = "requires a GPU adapter"]
+    fn caches_modules() {
+        let (device, _queue) = crate::test_util::headless_device();
+        let mut library = ShaderLibrary::with_preprocessor(ShaderPreprocessor::from_source(EmbeddedAssetSource::new(
+            &[("cs.wgsl", "@compute @workgroup_size(WORKGROUP_SIZE)\nfn cs_main() {}")],
+        )));
+        let first = library.get_or_create(&device, "cs", &[("WORKGROUP_SIZE", "8")], &[], None).unwrap();
+        let second = library.get_or_create(&device, "cs", &[("WORKGROUP_SIZE", "8")], &[], None).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        library.get_or_create(&device, "cs", &[("WORKGROUP_SIZE", "16")], &[], None).unwrap();
+        assert_eq!(library.len(), 2);
+        assert!(library.contains("cs", &[("WORKGROUP_SIZE", "16")], &[]));
+        let stats = library.stats();
+        assert_eq!((stats.module_hits, stats.module_misses), (1, 2));
+        assert_eq!(library.invalidate("cs"), 2);
+        assert!(library.is_empty());
+    }
+}
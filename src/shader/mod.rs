@@ -0,0 +1,224 @@
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+};
+use wgpu::{ShaderModule, ShaderModuleDescriptor, ShaderSource};
+
+mod asset_source;
+#[cfg(not(target_arch = "wasm32"))]
+pub use asset_source::write_embedded_shaders;
+pub use asset_source::{AssetSource, EmbeddedAssetSource, FsAssetSource};
+#[cfg(not(target_arch = "wasm32"))]
+mod bundle;
+#[cfg(not(target_arch = "wasm32"))]
+pub use bundle::{bundle_shaders, bundled_file_name, BundleOptions, BundledShader};
+mod error;
+pub use error::ShaderError;
+mod front_end;
+pub use front_end::ShaderKind;
+mod layout;
+#[doc(hidden)]
+pub use layout::size_of_pointee;
+pub use layout::{FieldLayout, StructMemberReflection, StructReflection, UniformLayout};
+mod library;
+pub use library::{ShaderKey, ShaderLibrary, ShaderLibraryStats};
+mod preprocessor;
+pub use preprocessor::{PreprocessedShader, ShaderPreprocessor, SourceLocation, SourceMap};
+mod reflection;
+pub use reflection::{BindingReflection, EntryPointReflection, ShaderReflection};
+#[cfg(not(any(target_arch = "wasm32", target_os = "ios", target_os = "android")))]
+mod watcher;
+#[cfg(not(any(target_arch = "wasm32", target_os = "ios", target_os = "android")))]
+pub use watcher::ShaderWatcher;
+
+#[allow(dead_code)]
+pub fn create_shader_module(
+    device: &wgpu::Device, shader_name: &'static str, label: Option<&str>,
+) -> Result<ShaderModule, ShaderError> {
+    insert_code_then_create(device, shader_name, None, label)
+}
+
+#[allow(dead_code)]
+pub fn insert_code_then_create(
+    device: &wgpu::Device, shader_name: &'static str, code_segment: Option<&str>, label: Option<&str>,
+) -> Result<ShaderModule, ShaderError> {
+    // @Kvark 20210402 ：Please don't use EXPERIMENTAL_TRANSLATION on Metal for this shader for now.
+    // let flags = ShaderFlags::VALIDATION | ShaderFlags::EXPERIMENTAL_TRANSLATION;
+    // let flags = ShaderFlags::VALIDATION;
+    // let flags = ShaderFlags::default();
+
+    let mut preprocessor = default_preprocessor();
+    if let Some(segment) = code_segment {
+        preprocessor = preprocessor.with_code_segment(segment);
+    }
+    let shader = preprocessor.process(&platform_shader_name(shader_name))?;
+
+    create_shader_module_from_preprocessed(device, &shader, label)
+}
+
+/// `segments` 如 `&[("field", "..."), ("color_map", "...")]`，
+/// 分别填充着色器中的 `#insert_code_segment field` 与 `#insert_code_segment color_map`
+#[allow(dead_code)]
+pub fn insert_segments_then_create(
+    device: &wgpu::Device, shader_name: &'static str, segments: &[(&str, &str)], label: Option<&str>,
+) -> Result<ShaderModule, ShaderError> {
+    let shader = default_preprocessor().with_segments(segments).process(&platform_shader_name(shader_name))?;
+    create_shader_module_from_preprocessed(device, &shader, label)
+}
+
+/// `defines` 如 `&[("WORKGROUP_SIZE", "16"), ("USE_STORAGE_TEX", "1")]`，
+/// 用于着色器中的 `#ifdef` / `#ifndef` 及宏替换
+#[allow(dead_code)]
+pub fn create_shader_module_with_defines(
+    device: &wgpu::Device, shader_name: &'static str, defines: &[(&str, &str)], label: Option<&str>,
+) -> Result<ShaderModule, ShaderError> {
+    let shader = default_preprocessor().with_defines(defines).process(&platform_shader_name(shader_name))?;
+    create_shader_module_from_preprocessed(device, &shader, label)
+}
+
+#[allow(dead_code)]
+pub fn create_shader_module_from_preprocessed(
+    device: &wgpu::Device, shader: &PreprocessedShader, label: Option<&str>,
+) -> Result<ShaderModule, ShaderError> {
+    // debug 模式下先用 naga 校验，避免无效的 WGSL 在 create_shader_module 内部 panic，并报告预处理前的源码位置；
+    // release 模式下省去这次重复的解析，交给 wgpu 校验
+    #[cfg(debug_assertions)]
+    validate_wgsl(shader)?;
+    Ok(device.create_shader_module(ShaderModuleDescriptor {
+        label,
+        source: ShaderSource::Wgsl(Cow::Borrowed(&shader.source)),
+    }))
+}
+
+/// 按扩展名选择 naga 前端：`.wgsl`、GLSL 的 `.vert` / `.frag` / `.comp` 及 `.spv`。
+/// WGSL 与 GLSL 都经过同样的 `#include` / `#define` 预处理，SPIR-V 直接读取。
+/// GLSL 与 SPIR-V 需要分别开启 `glsl` 与 `spirv` feature，否则返回 `ShaderError::MissingFeature`
+#[allow(dead_code)]
+pub fn create_shader_module_from_file(
+    device: &wgpu::Device, file: &str, label: Option<&str>,
+) -> Result<ShaderModule, ShaderError> {
+    create_shader_module_with_preprocessor(device, &default_preprocessor(), &platform_shader_name(file), label)
+}
+
+/// 与 `create_shader_module_from_file` 相同，使用自定义的预处理器（着色器目录、defines 等）
+#[allow(dead_code)]
+pub fn create_shader_module_with_preprocessor(
+    device: &wgpu::Device, preprocessor: &ShaderPreprocessor, file: &str, label: Option<&str>,
+) -> Result<ShaderModule, ShaderError> {
+    match ShaderKind::from_path(file) {
+        Some(ShaderKind::Wgsl) => {
+            create_shader_module_from_preprocessed(device, &preprocessor.process_file(file)?, label)
+        }
+        Some(ShaderKind::Glsl(stage)) => {
+            create_shader_module_from_glsl(device, &preprocessor.process_file(file)?, stage, label)
+        }
+        Some(ShaderKind::SpirV) => {
+            let data = preprocessor.source().read(Path::new(file)).map_err(|e| ShaderError::FileNotFound {
+                path: PathBuf::from(file),
+                reason: e.to_string(),
+                include_chain: vec![],
+            })?;
+            create_shader_module_from_spirv(device, Path::new(file), &data, label)
+        }
+        None => Err(ShaderError::UnknownShaderKind { path: PathBuf::from(file) }),
+    }
+}
+
+/// `shader` 是预处理后的 GLSL，`stage` 为其着色器阶段
+#[allow(dead_code)]
+pub fn create_shader_module_from_glsl(
+    device: &wgpu::Device, shader: &PreprocessedShader, stage: naga::ShaderStage, label: Option<&str>,
+) -> Result<ShaderModule, ShaderError> {
+    let (module, _) = front_end::parse_glsl(shader, stage)?;
+    Ok(device.create_shader_module(ShaderModuleDescriptor { label, source: ShaderSource::Naga(Cow::Owned(module)) }))
+}
+
+/// `path` 只用于错误信息
+#[allow(dead_code)]
+pub fn create_shader_module_from_spirv(
+    device: &wgpu::Device, path: &Path, data: &[u8], label: Option<&str>,
+) -> Result<ShaderModule, ShaderError> {
+    let (module, _) = front_end::parse_spirv(path, data)?;
+    Ok(device.create_shader_module(ShaderModuleDescriptor { label, source: ShaderSource::Naga(Cow::Owned(module)) }))
+}
+
+/// 用 naga 解析并校验预处理后的 GLSL，不需要 GPU
+#[allow(dead_code)]
+pub fn validate_glsl(shader: &PreprocessedShader, stage: naga::ShaderStage) -> Result<(), ShaderError> {
+    front_end::parse_glsl(shader, stage).map(|_| ())
+}
+
+/// 用 naga 解析并校验 SPIR-V，不需要 GPU
+#[allow(dead_code)]
+pub fn validate_spirv(path: &Path, data: &[u8]) -> Result<(), ShaderError> {
+    front_end::parse_spirv(path, data).map(|_| ())
+}
+
+/// 用 naga 解析并校验预处理后的 WGSL，不需要 GPU
+#[allow(dead_code)]
+pub fn validate_wgsl(shader: &PreprocessedShader) -> Result<(), ShaderError> {
+    parse_wgsl(shader).map(|_| ())
+}
+
+/// 用 naga 解析预处理后的 WGSL，获取入口函数、workgroup size 及所有 `@group/@binding` 资源，不需要 GPU
+#[allow(dead_code)]
+pub fn reflect(shader: &PreprocessedShader) -> Result<ShaderReflection, ShaderError> {
+    ShaderReflection::new(shader)
+}
+
+fn parse_wgsl(shader: &PreprocessedShader) -> Result<(naga::Module, naga::valid::ModuleInfo), ShaderError> {
+    let module = naga::front::wgsl::parse_str(&shader.source).map_err(|e| {
+        let line = e.location(&shader.source).map(|l| l.line_number as usize);
+        invalid_wgsl(shader, e.message().to_string(), line)
+    })?;
+    let info = validate_module(&module, Some(&shader.source))
+        .map_err(|(message, line)| invalid_wgsl(shader, message, line))?;
+    Ok((module, info))
+}
+
+// 出错时返回错误描述及在 `source` 中的行号
+fn validate_module(
+    module: &naga::Module, source: Option<&str>,
+) -> Result<naga::valid::ModuleInfo, (String, Option<usize>)> {
+    let mut validator =
+        naga::valid::Validator::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::all());
+    validator.validate(module).map_err(|e| {
+        let line = source.and_then(|source| e.location(source)).map(|l| l.line_number as usize);
+        // 将 error.source() 串起来，获得完整的错误描述
+        let mut message = e.to_string();
+        let mut inner = std::error::Error::source(&e);
+        while let Some(error) = inner {
+            message = format!("{message}: {error}");
+            inner = error.source();
+        }
+        (message, line)
+    })
+}
+
+fn invalid_wgsl(shader: &PreprocessedShader, message: String, line: Option<usize>) -> ShaderError {
+    let (location, include_chain) = shader.source_map.locate(line);
+    ShaderError::InvalidWgsl { message, location, include_chain }
+}
+
+// iOS 与 wasm 上使用的是离线预处理过的着色器
+fn default_preprocessor() -> ShaderPreprocessor {
+    // env!("CARGO_MANIFEST_DIR") 是编译时执行的，得到的是当前所编辑的库的所在路径，而不是项目的路径
+    // std::env::var("CARGO_MANIFEST_DIR") 在 xcode debug 时不存在
+    // std::env::current_dir() 在 xcode debug 时只能获得相对路径： “/”
+    let base_dir = app_surface::fs::application_root_dir();
+    if cfg!(target_arch = "wasm32") {
+        ShaderPreprocessor::new(base_dir)
+    } else if cfg!(target_os = "ios") {
+        ShaderPreprocessor::new(PathBuf::from(base_dir).join("shader-preprocessed-wgsl"))
+    } else {
+        ShaderPreprocessor::new(PathBuf::from(base_dir).join("shader-wgsl"))
+    }
+}
+
+fn platform_shader_name(shader_name: &str) -> String {
+    if cfg!(any(target_os = "ios", target_arch = "wasm32")) {
+        shader_name.replace('/', "_")
+    } else {
+        shader_name.to_string()
+    }
+}
@@ -0,0 +1,634 @@
+use super::{AssetSource, FsAssetSource, ShaderError};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+const SHADER_IMPORT: &str = "#include ";
+const SHADER_SEGMENT: &str = "#insert_code_segment";
+const SHADER_DEFINE: &str = "#define";
+const SHADER_IFDEF: &str = "#ifdef";
+const SHADER_IFNDEF: &str = "#ifndef";
+const SHADER_ELSE: &str = "#else";
+const SHADER_ENDIF: &str = "#endif";
+
+/// 预处理前的源码位置，`line` 从 1 开始
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SourceLocation {
+    /// 相对于预处理器根目录的文件路径
+    pub file: PathBuf,
+    pub line: usize,
+}
+
+impl std::fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.file.display(), self.line)
+    }
+}
+
+/// 预处理输出的每一行到原始文件及行号的映射
+#[derive(Clone, Debug, Default)]
+pub struct SourceMap {
+    lines: Vec<SourceLocation>,
+    include_chains: HashMap<PathBuf, Vec<PathBuf>>,
+}
+
+impl SourceMap {
+    /// `line` 是预处理输出中从 1 开始的行号，与 naga 报错中的行号一致
+    pub fn lookup(&self, line: usize) -> Option<&SourceLocation> {
+        if line == 0 {
+            return None;
+        }
+        self.lines.get(line - 1)
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, SourceLocation> {
+        self.lines.iter()
+    }
+
+    /// 参与预处理的所有文件，包括顶层文件
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        self.include_chains.keys().map(|file| file.as_path())
+    }
+
+    /// `file` 是被哪些文件逐级 `#include` 进来的，从顶层文件开始
+    pub fn include_chain(&self, file: &Path) -> &[PathBuf] {
+        self.include_chains.get(file).map(|chain| chain.as_slice()).unwrap_or(&[])
+    }
+
+    /// 预处理输出中的行号对应的原始位置及其 include 链，用于生成错误信息；`line` 为 None 时两者都为空
+    pub fn locate(&self, line: Option<usize>) -> (Option<SourceLocation>, Vec<PathBuf>) {
+        match line.and_then(|line| self.lookup(line)) {
+            Some(location) => (Some(location.clone()), self.include_chain(&location.file).to_vec()),
+            None => (None, vec![]),
+        }
+    }
+
+    fn push(&mut self, file: &Path, line: usize) {
+        self.lines.push(SourceLocation { file: file.to_path_buf(), line });
+    }
+}
+
+/// 预处理后的最终 WGSL 及其 source map
+#[derive(Clone, Debug)]
+pub struct PreprocessedShader {
+    pub source: String,
+    pub source_map: SourceMap,
+}
+
+/// 不依赖 wgpu::Device 的着色器预处理器：
+/// 展开 `#include`，并将 `#insert_code_segment` 替换为代码片段
+///
+/// `#include` 的路径优先相对于当前文件所在目录解析，其次相对于根目录；
+/// 同一个文件在一个模块中只会被展开一次（延迟处理条件编译时按分支判断），循环引用会返回 `ShaderError::IncludeCycle`
+///
+/// 支持 `#define NAME value`, `#ifdef`, `#ifndef`, `#else`, `#endif` 条件编译，
+/// 宏也可以通过 `with_defines` 从 Rust 端传入，代码中与宏同名的标识符会被替换为宏的值；
+/// 带参数的宏不做处理，原样输出
+///
+/// 代码片段标记可以具名，如 `#insert_code_segment color_map`，由 `with_segments` 按名称填充；
+/// 不带名称的标记由 `with_code_segment` 填充。着色器中有标记却没有提供代码，
+/// 或者提供了代码却没有对应的标记，都会返回错误
+#[derive(Clone)]
+pub struct ShaderPreprocessor {
+    source: Arc<dyn AssetSource>,
+    segments: Vec<(String, String)>,
+    defines: Vec<(String, String)>,
+    defer_directives: bool,
+}
+
+#[allow(dead_code)]
+impl ShaderPreprocessor {
+    /// `root` 是着色器根目录，如 `<app>/shader-wgsl`
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        ShaderPreprocessor::from_source(FsAssetSource::new(root))
+    }
+
+    /// 从自定义的 `AssetSource` 读取着色器，如内嵌到二进制中的 `EmbeddedAssetSource`
+    pub fn from_source(source: impl AssetSource + 'static) -> Self {
+        ShaderPreprocessor { source: Arc::new(source), segments: vec![], defines: vec![], defer_directives: false }
+    }
+
+    /// 如 `&[("WORKGROUP_SIZE", "16"), ("USE_STORAGE_TEX", "1")]`
+    pub fn with_defines(mut self, defines: &[(&str, &str)]) -> Self {
+        for (name, value) in defines {
+            self.defines.push((name.to_string(), value.to_string()));
+        }
+        self
+    }
+
+    /// 填充不带名称的 `#insert_code_segment` 标记
+    pub fn with_code_segment(self, segment: &str) -> Self {
+        self.with_segments(&[("", segment)])
+    }
+
+    /// 如 `&[("field", "..."), ("color_map", "...")]`，填充 `#insert_code_segment <name>` 标记
+    pub fn with_segments(mut self, segments: &[(&str, &str)]) -> Self {
+        for (name, code) in segments {
+            self.segments.retain(|(n, _)| n != name);
+            self.segments.push((name.to_string(), code.to_string()));
+        }
+        self
+    }
+
+    /// 只展开 `#include`，条件编译、`#define` 及代码片段标记原样保留，留给运行时再处理一遍。
+    /// 用于离线生成 iOS 与 wasm 上使用的 `shader-preprocessed-wgsl`
+    pub fn with_deferred_directives(mut self, defer: bool) -> Self {
+        self.defer_directives = defer;
+        self
+    }
+
+    pub fn source(&self) -> &dyn AssetSource {
+        self.source.as_ref()
+    }
+
+    pub fn defines(&self) -> &[(String, String)] {
+        &self.defines
+    }
+
+    /// 读取 `<shader_name>.wgsl` 并预处理
+    pub fn process(&self, shader_name: &str) -> Result<PreprocessedShader, ShaderError> {
+        self.process_file(format!("{shader_name}.wgsl"))
+    }
+
+    /// 读取任意扩展名的文本文件并预处理，如 GLSL 的 `.vert` / `.frag` / `.comp`
+    pub fn process_file(&self, file: impl AsRef<Path>) -> Result<PreprocessedShader, ShaderError> {
+        let file = file.as_ref();
+        let code = self.load_source(file, &[])?;
+        self.process_source(file, &code)
+    }
+
+    /// 预处理已读取到内存中的源码，`file` 只用于生成 source map 及解析相对路径的 `#include`
+    pub fn process_source(&self, file: &Path, source: &str) -> Result<PreprocessedShader, ShaderError> {
+        let mut output = PreprocessedShader { source: String::new(), source_map: SourceMap::default() };
+        let mut state = ParseState::default();
+        state.included.insert(normalize_path(file));
+        if !self.defer_directives {
+            state.defines = self.defines.iter().cloned().collect();
+        }
+        self.parse_shader_source(&normalize_path(file), source, &mut state, &mut output)?;
+
+        if let Some((name, _)) = self.segments.iter().find(|(name, _)| !state.used_segments.contains(name)) {
+            return Err(ShaderError::MissingSegmentMarker {
+                name: name.clone(),
+                include_chain: vec![file.to_path_buf()],
+            });
+        }
+        Ok(output)
+    }
+
+    fn parse_shader_source(
+        &self, file: &Path, source: &str, state: &mut ParseState, output: &mut PreprocessedShader,
+    ) -> Result<(), ShaderError> {
+        output.source_map.include_chains.entry(file.to_path_buf()).or_insert_with(|| state.chain.clone());
+        state.chain.push(file.to_path_buf());
+
+        // 条件编译块只在当前文件内生效，`#ifdef` 与 `#endif` 必须在同一个文件中配对
+        let mut conditionals: Vec<Conditional> = vec![];
+        let mut deferred_branches: Vec<DeferredBranch> = vec![];
+        for (index, line) in source.lines().enumerate() {
+            let location = SourceLocation { file: file.to_path_buf(), line: index + 1 };
+            let trimmed = line.trim();
+            if self.defer_directives && is_deferrable_directive(trimmed) {
+                track_deferred_branch(trimmed, &mut deferred_branches, &mut state.included);
+                push_line(output, line, file, index + 1);
+                continue;
+            }
+            let ifdef = directive_argument(trimmed, SHADER_IFDEF);
+            let ifndef = directive_argument(trimmed, SHADER_IFNDEF);
+            if let Some(name) = ifdef.or(ifndef) {
+                if name.is_empty() {
+                    return Err(invalid_directive("missing macro name", location, state));
+                }
+                let defined = state.defines.contains_key(name);
+                conditionals.push(Conditional { condition: defined == ifdef.is_some(), in_else: false, location });
+                continue;
+            }
+            if directive_argument(trimmed, SHADER_ELSE).is_some() {
+                match conditionals.last_mut() {
+                    Some(c) if !c.in_else => c.in_else = true,
+                    _ => return Err(invalid_directive("`#else` without matching `#ifdef`", location, state)),
+                }
+                continue;
+            }
+            if directive_argument(trimmed, SHADER_ENDIF).is_some() {
+                if conditionals.pop().is_none() {
+                    return Err(invalid_directive("`#endif` without matching `#ifdef`", location, state));
+                }
+                continue;
+            }
+            if !conditionals.iter().all(Conditional::is_active) {
+                continue;
+            }
+
+            if let Some(define) = directive_argument(trimmed, SHADER_DEFINE) {
+                let (name, value) = define.split_once(char::is_whitespace).unwrap_or((define, ""));
+                if name.is_empty() {
+                    return Err(invalid_directive("missing macro name", location, state));
+                }
+                if name.contains('(') {
+                    // 带参数的宏，如 GLSL 中的 `#define SQ(x) ((x)*(x))`，原样保留给 naga 的 GLSL 前端处理
+                    push_line(output, line, file, index + 1);
+                } else {
+                    state.defines.insert(name.to_string(), value.trim().to_string());
+                }
+            } else if let Some(imports) = line.strip_prefix(SHADER_IMPORT) {
+                // For each import, get the source, and recurse.
+                for import in imports.split(',') {
+                    let import = import.trim().replace('"', "");
+                    if let Some((include, code)) = self.resolve_include(file, &import, state)? {
+                        state.included.insert(include.clone());
+                        self.parse_shader_source(&include, &code, state, output)?;
+                    }
+                }
+            } else if let Some(pos) = line.find(SHADER_SEGMENT) {
+                let name = segment_name(&line[pos + SHADER_SEGMENT.len()..]);
+                let segment = match self.segments.iter().find(|(n, _)| n == name) {
+                    Some((_, segment)) => segment,
+                    None => {
+                        return Err(ShaderError::MissingSegmentCode {
+                            name: name.to_string(),
+                            location,
+                            include_chain: state.include_chain(),
+                        });
+                    }
+                };
+                for segment_line in segment.lines() {
+                    push_line(output, segment_line, file, index + 1);
+                }
+                state.used_segments.insert(name.to_string());
+            } else {
+                push_line(output, &substitute_defines(line, &state.defines), file, index + 1);
+            }
+        }
+
+        if let Some(unterminated) = conditionals.pop() {
+            return Err(invalid_directive("unterminated `#ifdef`", unterminated.location, state));
+        }
+        state.chain.pop();
+        Ok(())
+    }
+
+    // 优先相对于当前文件所在目录查找，找不到时再相对于根目录查找（兼容旧的写法）；
+    // 已经被引入过的文件返回 None，保证每个文件在一个模块中只出现一次
+    fn resolve_include(
+        &self, file: &Path, include: &str, state: &ParseState,
+    ) -> Result<Option<(PathBuf, String)>, ShaderError> {
+        let relative = normalize_path(&file.parent().unwrap_or_else(|| Path::new("")).join(include));
+        let from_root = normalize_path(Path::new(include));
+        let candidates = if relative == from_root { vec![relative] } else { vec![relative, from_root] };
+
+        let mut first_error = None;
+        for candidate in candidates {
+            if state.chain.contains(&candidate) {
+                return Err(ShaderError::IncludeCycle { path: candidate, include_chain: state.chain.clone() });
+            }
+            if state.included.contains(&candidate) {
+                return Ok(None);
+            }
+            match self.load_source(&candidate, &state.chain) {
+                Ok(code) => return Ok(Some((candidate, code))),
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        Err(first_error.unwrap())
+    }
+
+    fn load_source(&self, file: &Path, chain: &[PathBuf]) -> Result<String, ShaderError> {
+        self.source.read_to_string(file).map_err(|e| ShaderError::FileNotFound {
+            path: file.to_path_buf(),
+            reason: e.to_string(),
+            include_chain: chain.to_vec(),
+        })
+    }
+}
+
+impl std::fmt::Debug for ShaderPreprocessor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ShaderPreprocessor")
+            .field("segments", &self.segments)
+            .field("defines", &self.defines)
+            .field("defer_directives", &self.defer_directives)
+            .finish()
+    }
+}
+
+struct Conditional {
+    condition: bool,
+    in_else: bool,
+    // `#ifdef` / `#ifndef` 所在位置，用于报告未闭合的条件块
+    location: SourceLocation,
+}
+
+impl Conditional {
+    fn is_active(&self) -> bool {
+        self.condition != self.in_else
+    }
+}
+
+// 延迟处理条件编译时，`#ifdef` 之前已展开的文件，以及 `#else` 之前那个分支展开后的结果
+struct DeferredBranch {
+    before: HashSet<PathBuf>,
+    first_arm: Option<HashSet<PathBuf>>,
+}
+
+#[derive(Default)]
+struct ParseState {
+    // 当前正在展开的 `#include` 链，用于检测循环引用
+    chain: Vec<PathBuf>,
+    // 已经展开过的文件
+    included: HashSet<PathBuf>,
+    defines: HashMap<String, String>,
+    used_segments: HashSet<String>,
+}
+
+impl ParseState {
+    // chain 的最后一项是当前正在处理的文件自身，不属于它的 include 链
+    fn include_chain(&self) -> Vec<PathBuf> {
+        self.chain[..self.chain.len().saturating_sub(1)].to_vec()
+    }
+}
+
+fn is_deferrable_directive(line: &str) -> bool {
+    [SHADER_DEFINE, SHADER_IFDEF, SHADER_IFNDEF, SHADER_ELSE, SHADER_ENDIF]
+        .iter()
+        .any(|directive| directive_argument(line, directive).is_some())
+        || line.contains(SHADER_SEGMENT)
+}
+
+// 条件编译留给运行时处理时，哪个分支生效还不确定，只展开一次的判断需要按分支进行：
+// `#else` 分支不受 `#ifdef` 分支中的 `#include` 影响；`#endif` 之后，只有每个分支中都展开过的文件才算已展开
+fn track_deferred_branch(line: &str, branches: &mut Vec<DeferredBranch>, included: &mut HashSet<PathBuf>) {
+    if directive_argument(line, SHADER_IFDEF).or_else(|| directive_argument(line, SHADER_IFNDEF)).is_some() {
+        branches.push(DeferredBranch { before: included.clone(), first_arm: None });
+    } else if directive_argument(line, SHADER_ELSE).is_some() {
+        if let Some(branch) = branches.last_mut() {
+            branch.first_arm = Some(std::mem::replace(included, branch.before.clone()));
+        }
+    } else if directive_argument(line, SHADER_ENDIF).is_some() {
+        if let Some(branch) = branches.pop() {
+            match branch.first_arm {
+                Some(first_arm) => included.retain(|file| first_arm.contains(file)),
+                // 没有 `#else` 时，`#ifdef` 分支在运行时可能被跳过
+                None => *included = branch.before,
+            }
+        }
+    }
+}
+
+// `line` 以 `directive` 开头且其后为空白或行尾时，返回其后的参数
+fn directive_argument<'a>(line: &'a str, directive: &str) -> Option<&'a str> {
+    let rest = line.strip_prefix(directive)?;
+    if rest.is_empty() || rest.starts_with(char::is_whitespace) {
+        Some(rest.trim())
+    } else {
+        None
+    }
+}
+
+// 标记后的第一个标识符为片段名称，行尾注释不算；不是合法标识符时视为不带名称的标记
+fn segment_name(rest: &str) -> &str {
+    let rest = rest.split("//").next().unwrap_or("");
+    let is_identifier = |name: &&str| {
+        name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+    };
+    rest.split_whitespace().next().filter(is_identifier).unwrap_or("")
+}
+
+fn invalid_directive(message: &str, location: SourceLocation, state: &ParseState) -> ShaderError {
+    ShaderError::InvalidDirective { message: message.to_string(), location, include_chain: state.include_chain() }
+}
+
+// 按标识符整体替换，避免替换掉更长标识符中的一部分
+fn substitute_defines<'a>(line: &'a str, defines: &HashMap<String, String>) -> Cow<'a, str> {
+    if defines.is_empty() {
+        return Cow::Borrowed(line);
+    }
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+    let mut output = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(start) = rest.find(is_word_char) {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest.find(|c: char| !is_word_char(c)).unwrap_or(rest.len());
+        let word = &rest[..end];
+        // 以数字开头的是字面量，如 `16u`, `0x1F`
+        match defines.get(word) {
+            Some(value) if !word.starts_with(|c: char| c.is_ascii_digit()) => output.push_str(value),
+            _ => output.push_str(word),
+        }
+        rest = &rest[end..];
+    }
+    output.push_str(rest);
+    Cow::Owned(output)
+}
+
+// 按字面处理 `.` 与 `..`，不访问文件系统
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push("..");
+                }
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+fn push_line(output: &mut PreprocessedShader, line: &str, file: &Path, line_number: usize) {
+    output.source.push_str(line);
+    output.source.push('\n');
+    output.source_map.push(file, line_number);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shader::EmbeddedAssetSource;
+
+    fn preprocessor(files: &[(&'static str, &'static str)]) -> ShaderPreprocessor {
+        ShaderPreprocessor::from_source(EmbeddedAssetSource::new(files))
+    }
+
+    fn lines(shader: &PreprocessedShader) -> Vec<&str> {
+        shader.source.lines().collect()
+    }
+
+    #[test]
+    fn maps_output_lines_to_source_files() {
+        let shader = preprocessor(&[
+            ("main.wgsl", "// main\n#include \"lib/a.wgsl\"\nmain\n#insert_code_segment"),
+            ("lib/a.wgsl", "#include \"b.wgsl\"\na"),
+            ("lib/b.wgsl", "\nb"),
+        ])
+        .with_code_segment("s1\ns2")
+        .process("main")
+        .unwrap();
+        let map = &shader.source_map;
+        let located: Vec<(String, usize)> =
+            map.iter().map(|l| (l.file.to_string_lossy().replace('\\', "/"), l.line)).collect();
+        let expected = [
+            ("main.wgsl", 1),
+            ("lib/b.wgsl", 1),
+            ("lib/b.wgsl", 2),
+            ("lib/a.wgsl", 2),
+            ("main.wgsl", 3),
+            ("main.wgsl", 4),
+            ("main.wgsl", 4),
+        ];
+        assert_eq!(located, expected.iter().map(|(f, l)| (f.to_string(), *l)).collect::<Vec<_>>());
+        assert_eq!(map.len(), shader.source.lines().count());
+        assert!(map.lookup(0).is_none());
+        assert_eq!(map.lookup(3).unwrap().to_string(), "lib/b.wgsl:2");
+        assert_eq!(
+            map.include_chain(Path::new("lib/b.wgsl")),
+            [PathBuf::from("main.wgsl"), PathBuf::from("lib/a.wgsl")]
+        );
+        let (location, include_chain) = map.locate(Some(4));
+        assert_eq!(location.unwrap().to_string(), "lib/a.wgsl:2");
+        assert_eq!(include_chain, [PathBuf::from("main.wgsl")]);
+        assert_eq!(map.locate(None), (None, vec![]));
+        assert_eq!(map.locate(Some(100)), (None, vec![]));
+    }
+
+    #[test]
+    fn includes_each_file_once() {
+        let shader = preprocessor(&[
+            ("main.wgsl", "#include \"common.wgsl\", \"noise.wgsl\"\nmain"),
+            ("noise.wgsl", "#include \"common.wgsl\"\nnoise"),
+            ("common.wgsl", "common"),
+        ])
+        .process("main")
+        .unwrap();
+        assert_eq!(lines(&shader), ["common", "noise", "main"]);
+    }
+
+    #[test]
+    fn includes_relative_to_the_current_file() {
+        let shader = preprocessor(&[
+            ("fluid/collide.wgsl", "#include \"lbm.wgsl\"\n#include \"common.wgsl\"\ncollide"),
+            ("fluid/lbm.wgsl", "lbm"),
+            ("common.wgsl", "common"),
+        ])
+        .process("fluid/collide")
+        .unwrap();
+        assert_eq!(lines(&shader), ["lbm", "common", "collide"]);
+    }
+
+    #[test]
+    fn reports_include_cycles() {
+        let result = preprocessor(&[("a.wgsl", "#include \"b.wgsl\""), ("b.wgsl", "#include \"a.wgsl\"")]).process("a");
+        match result {
+            Err(ShaderError::IncludeCycle { path, include_chain }) => {
+                assert_eq!(path, PathBuf::from("a.wgsl"));
+                assert_eq!(include_chain, [PathBuf::from("a.wgsl"), PathBuf::from("b.wgsl")]);
+            }
+            other => panic!("expected an include cycle, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn evaluates_conditionals_and_defines() {
+        let source =
+            "#define LOCAL 2\n#ifdef USE_TEX\ntex LOCAL\n#else\nno_tex\n#endif\n#ifndef USE_TEX\nndef\n#endif\nSIZE";
+        let files = [("main.wgsl", source)];
+        let shader = preprocessor(&files).with_defines(&[("USE_TEX", ""), ("SIZE", "16")]).process("main").unwrap();
+        assert_eq!(lines(&shader), ["tex 2", "16"]);
+
+        let shader = preprocessor(&files).process("main").unwrap();
+        assert_eq!(lines(&shader), ["no_tex", "ndef", "SIZE"]);
+    }
+
+    #[test]
+    fn reports_unbalanced_conditionals() {
+        for source in ["#ifdef A\nx", "#endif", "#else", "#ifdef A\n#else\n#else\n#endif", "#ifdef\n#endif"] {
+            let result = preprocessor(&[("main.wgsl", source)]).process("main");
+            assert!(matches!(result, Err(ShaderError::InvalidDirective { .. })), "{source:?}: {result:?}");
+        }
+    }
+
+    #[test]
+    fn substitutes_whole_identifiers_only() {
+        let defines: HashMap<String, String> =
+            [("N", "4"), ("SIZE", "16u"), ("1", "x")].iter().map(|(n, v)| (n.to_string(), v.to_string())).collect();
+        assert_eq!(
+            substitute_defines("let a = N * SIZE + N_MAX + SIZE_2;", &defines),
+            "let a = 4 * 16u + N_MAX + SIZE_2;"
+        );
+        assert_eq!(substitute_defines("array<f32, N>(1, 0x1F)", &defines), "array<f32, 4>(1, 0x1F)");
+        assert!(matches!(substitute_defines("N", &HashMap::new()), Cow::Borrowed("N")));
+    }
+
+    #[test]
+    fn fills_segment_markers() {
+        let source = "#insert_code_segment\n  #insert_code_segment color_map // 颜色映射\n#insert_code_segment //";
+        let shader = preprocessor(&[("main.wgsl", source)])
+            .with_code_segment("anonymous")
+            .with_segments(&[("color_map", "map_a\nmap_b")])
+            .process("main")
+            .unwrap();
+        assert_eq!(lines(&shader), ["anonymous", "map_a", "map_b", "anonymous"]);
+    }
+
+    #[test]
+    fn reports_missing_segments_and_markers() {
+        let files = [("main.wgsl", "\n#insert_code_segment field")];
+        match preprocessor(&files).process("main") {
+            Err(ShaderError::MissingSegmentCode { name, location, .. }) => {
+                assert_eq!(name, "field");
+                assert_eq!(location, SourceLocation { file: PathBuf::from("main.wgsl"), line: 2 });
+            }
+            other => panic!("expected missing segment code, got {:?}", other),
+        }
+        let result = preprocessor(&files).with_segments(&[("field", ""), ("color_map", "")]).process("main");
+        assert!(matches!(result, Err(ShaderError::MissingSegmentMarker { name, .. }) if name == "color_map"));
+    }
+
+    #[test]
+    fn defers_directives() {
+        let shader = preprocessor(&[
+            ("main.wgsl", "#define N 4\n#ifdef A\n#include \"a.wgsl\"\n#endif\n#insert_code_segment\nN"),
+            ("a.wgsl", "a"),
+        ])
+        .with_deferred_directives(true)
+        .process("main")
+        .unwrap();
+        assert_eq!(lines(&shader), ["#define N 4", "#ifdef A", "a", "#endif", "#insert_code_segment", "N"]);
+    }
+
+    #[test]
+    fn deferred_include_once_is_tracked_per_branch() {
+        let source = "#ifdef A\n#include \"common.wgsl\"\n#else\n#include \"common.wgsl\"\n#endif\n\
+                      #include \"common.wgsl\"\n#ifdef B\n#include \"b.wgsl\"\n#endif\n#include \"b.wgsl\"";
+        let shader = preprocessor(&[("main.wgsl", source), ("common.wgsl", "common"), ("b.wgsl", "b")])
+            .with_deferred_directives(true)
+            .process("main")
+            .unwrap();
+        // common 在两个分支中都展开了，之后不再展开；b 只在可能被跳过的分支中展开过，之后还需要展开
+        assert_eq!(lines(&shader), ["#ifdef A", "common", "#else", "common", "#endif", "#ifdef B", "b", "#endif", "b"]);
+    }
+
+    #[test]
+    fn keeps_function_like_defines() {
+        let shader = preprocessor(&[("main.comp", "#define SQ(x) ((x)*(x))\n#define N 4\nSQ(N)")])
+            .process_file("main.comp")
+            .unwrap();
+        assert_eq!(lines(&shader), ["#define SQ(x) ((x)*(x))", "SQ(4)"]);
+    }
+}
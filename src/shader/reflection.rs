@@ -0,0 +1,432 @@
+use super::{PreprocessedShader, ShaderError, SourceLocation, SourceMap, StructReflection};
+use std::num::NonZeroU32;
+
+/// 着色器入口函数
+#[derive(Clone, Debug)]
+pub struct EntryPointReflection {
+    pub name: String,
+    pub stage: wgpu::ShaderStages,
+    /// 只有 compute 入口有意义，其它阶段为 [0, 0, 0]
+    pub workgroup_size: [u32; 3],
+}
+
+/// 着色器中声明的 `@group(x) @binding(y)` 资源
+#[derive(Clone, Debug)]
+pub struct BindingReflection {
+    pub group: u32,
+    pub binding: u32,
+    pub name: Option<String>,
+    /// 由 WGSL 类型推导出的绑定类型。
+    /// 纹理的 `filterable` 无法从 WGSL 得知，总是 `true`
+    pub ty: wgpu::BindingType,
+    /// binding_array 的元素个数
+    pub count: Option<NonZeroU32>,
+    /// 实际使用了此资源的着色器阶段，没有被任何入口使用时为空
+    pub visibility: wgpu::ShaderStages,
+    /// 声明此资源的原始文件及行号
+    pub location: Option<SourceLocation>,
+    // 在预处理输出中的行号
+    pub(crate) line: Option<usize>,
+}
+
+/// 由 naga 解析预处理后的 WGSL 得到的反射信息
+#[derive(Debug)]
+pub struct ShaderReflection {
+    pub entry_points: Vec<EntryPointReflection>,
+    /// 按 (group, binding) 排序
+    pub bindings: Vec<BindingReflection>,
+    /// 具名的结构体及其 WGSL 内存布局
+    pub structs: Vec<StructReflection>,
+    pub(crate) module: naga::Module,
+    pub(crate) source_map: SourceMap,
+}
+
+#[allow(dead_code)]
+impl ShaderReflection {
+    pub fn new(shader: &PreprocessedShader) -> Result<Self, ShaderError> {
+        let (module, info) = super::parse_wgsl(shader)?;
+
+        let entry_points = module
+            .entry_points
+            .iter()
+            .map(|ep| EntryPointReflection {
+                name: ep.name.clone(),
+                stage: stage_to_wgpu(ep.stage),
+                workgroup_size: ep.workgroup_size,
+            })
+            .collect();
+
+        let mut bindings: Vec<BindingReflection> = vec![];
+        for (handle, var) in module.global_variables.iter() {
+            let resource_binding = match &var.binding {
+                Some(binding) => binding,
+                None => continue,
+            };
+            let (ty, count) = match binding_type(&module, var) {
+                Some(ty) => ty,
+                None => continue,
+            };
+            let mut visibility = wgpu::ShaderStages::NONE;
+            for (index, ep) in module.entry_points.iter().enumerate() {
+                if !info.get_entry_point(index)[handle].is_empty() {
+                    visibility |= stage_to_wgpu(ep.stage);
+                }
+            }
+            let span = module.global_variables.get_span(handle);
+            let line = if span.is_defined() { Some(span.location(&shader.source).line_number as usize) } else { None };
+            let (location, _) = shader.source_map.locate(line);
+            bindings.push(BindingReflection {
+                group: resource_binding.group,
+                binding: resource_binding.binding,
+                name: var.name.clone(),
+                ty,
+                count,
+                visibility,
+                location,
+                line,
+            });
+        }
+        bindings.sort_by_key(|b| (b.group, b.binding));
+
+        let structs = super::layout::reflect_structs(&module, shader);
+
+        Ok(ShaderReflection { entry_points, bindings, structs, module, source_map: shader.source_map.clone() })
+    }
+
+    pub fn entry_point(&self, name: &str) -> Option<&EntryPointReflection> {
+        self.entry_points.iter().find(|ep| ep.name == name)
+    }
+
+    pub fn binding(&self, group: u32, binding: u32) -> Option<&BindingReflection> {
+        self.bindings.iter().find(|b| b.group == group && b.binding == binding)
+    }
+
+    pub fn binding_by_name(&self, name: &str) -> Option<&BindingReflection> {
+        self.bindings.iter().find(|b| b.name.as_deref() == Some(name))
+    }
+
+    pub fn group_bindings(&self, group: u32) -> impl Iterator<Item = &BindingReflection> {
+        self.bindings.iter().filter(move |b| b.group == group)
+    }
+
+    /// 检查手动设置的 visibility 是否覆盖了着色器中实际使用资源的阶段，
+    /// `visibilities[i]` 对应 `@group(group) @binding(i)`，与 `BindingGroupSetting::new` 的约定一致
+    pub fn check_visibilities(&self, group: u32, visibilities: &[wgpu::ShaderStages]) -> Result<(), ShaderError> {
+        for b in self.group_bindings(group) {
+            if b.visibility.is_empty() {
+                continue;
+            }
+            match visibilities.get(b.binding as usize) {
+                Some(given) if given.contains(b.visibility) => {}
+                Some(given) => {
+                    return Err(self.binding_mismatch(
+                        b,
+                        format!("visibility {:?} does not include the stages using it: {:?}", given, b.visibility),
+                    ))
+                }
+                None => return Err(self.binding_mismatch(b, "no visibility was provided".to_string())),
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn binding_mismatch(&self, b: &BindingReflection, message: String) -> ShaderError {
+        let (location, include_chain) = self.source_map.locate(b.line);
+        ShaderError::BindingMismatch { group: b.group, binding: b.binding, message, location, include_chain }
+    }
+}
+
+fn stage_to_wgpu(stage: naga::ShaderStage) -> wgpu::ShaderStages {
+    match stage {
+        naga::ShaderStage::Vertex => wgpu::ShaderStages::VERTEX,
+        naga::ShaderStage::Fragment => wgpu::ShaderStages::FRAGMENT,
+        naga::ShaderStage::Compute => wgpu::ShaderStages::COMPUTE,
+    }
+}
+
+fn binding_type(module: &naga::Module, var: &naga::GlobalVariable) -> Option<(wgpu::BindingType, Option<NonZeroU32>)> {
+    let (ty, count) = match module.types[var.ty].inner {
+        naga::TypeInner::BindingArray { base, size } => {
+            let count = match size {
+                naga::ArraySize::Constant(c) => match module.constants[c].inner {
+                    naga::ConstantInner::Scalar { value: naga::ScalarValue::Uint(v), .. } => NonZeroU32::new(v as u32),
+                    naga::ConstantInner::Scalar { value: naga::ScalarValue::Sint(v), .. } => NonZeroU32::new(v as u32),
+                    _ => None,
+                },
+                naga::ArraySize::Dynamic => None,
+            };
+            (base, count)
+        }
+        _ => (var.ty, None),
+    };
+
+    let binding_ty = match var.space {
+        naga::AddressSpace::Uniform => wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: wgpu::BufferSize::new(module.types[ty].inner.size(&module.constants) as u64),
+        },
+        naga::AddressSpace::Storage { access } => wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: !access.contains(naga::StorageAccess::STORE) },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        naga::AddressSpace::Handle => match module.types[ty].inner {
+            naga::TypeInner::Sampler { comparison } => wgpu::BindingType::Sampler(if comparison {
+                wgpu::SamplerBindingType::Comparison
+            } else {
+                wgpu::SamplerBindingType::Filtering
+            }),
+            naga::TypeInner::Image { dim, arrayed, class } => {
+                let view_dimension = view_dimension(dim, arrayed);
+                match class {
+                    naga::ImageClass::Sampled { kind, multi } => wgpu::BindingType::Texture {
+                        sample_type: match kind {
+                            naga::ScalarKind::Sint => wgpu::TextureSampleType::Sint,
+                            naga::ScalarKind::Uint => wgpu::TextureSampleType::Uint,
+                            _ => wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        view_dimension,
+                        multisampled: multi,
+                    },
+                    naga::ImageClass::Depth { multi } => wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Depth,
+                        view_dimension,
+                        multisampled: multi,
+                    },
+                    naga::ImageClass::Storage { format, access } => wgpu::BindingType::StorageTexture {
+                        access: if !access.contains(naga::StorageAccess::STORE) {
+                            wgpu::StorageTextureAccess::ReadOnly
+                        } else if !access.contains(naga::StorageAccess::LOAD) {
+                            wgpu::StorageTextureAccess::WriteOnly
+                        } else {
+                            wgpu::StorageTextureAccess::ReadWrite
+                        },
+                        format: storage_format_to_wgpu(format),
+                        view_dimension,
+                    },
+                }
+            }
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some((binding_ty, count))
+}
+
+fn view_dimension(dim: naga::ImageDimension, arrayed: bool) -> wgpu::TextureViewDimension {
+    match (dim, arrayed) {
+        (naga::ImageDimension::D1, _) => wgpu::TextureViewDimension::D1,
+        (naga::ImageDimension::D2, false) => wgpu::TextureViewDimension::D2,
+        (naga::ImageDimension::D2, true) => wgpu::TextureViewDimension::D2Array,
+        (naga::ImageDimension::D3, _) => wgpu::TextureViewDimension::D3,
+        (naga::ImageDimension::Cube, false) => wgpu::TextureViewDimension::Cube,
+        (naga::ImageDimension::Cube, true) => wgpu::TextureViewDimension::CubeArray,
+    }
+}
+
+fn storage_format_to_wgpu(format: naga::StorageFormat) -> wgpu::TextureFormat {
+    use naga::StorageFormat as Sf;
+    use wgpu::TextureFormat as Tf;
+    match format {
+        Sf::R8Unorm => Tf::R8Unorm,
+        Sf::R8Snorm => Tf::R8Snorm,
+        Sf::R8Uint => Tf::R8Uint,
+        Sf::R8Sint => Tf::R8Sint,
+        Sf::R16Uint => Tf::R16Uint,
+        Sf::R16Sint => Tf::R16Sint,
+        Sf::R16Float => Tf::R16Float,
+        Sf::Rg8Unorm => Tf::Rg8Unorm,
+        Sf::Rg8Snorm => Tf::Rg8Snorm,
+        Sf::Rg8Uint => Tf::Rg8Uint,
+        Sf::Rg8Sint => Tf::Rg8Sint,
+        Sf::R32Uint => Tf::R32Uint,
+        Sf::R32Sint => Tf::R32Sint,
+        Sf::R32Float => Tf::R32Float,
+        Sf::Rg16Uint => Tf::Rg16Uint,
+        Sf::Rg16Sint => Tf::Rg16Sint,
+        Sf::Rg16Float => Tf::Rg16Float,
+        Sf::Rgba8Unorm => Tf::Rgba8Unorm,
+        Sf::Rgba8Snorm => Tf::Rgba8Snorm,
+        Sf::Rgba8Uint => Tf::Rgba8Uint,
+        Sf::Rgba8Sint => Tf::Rgba8Sint,
+        Sf::Rgb10a2Unorm => Tf::Rgb10a2Unorm,
+        Sf::Rg11b10Float => Tf::Rg11b10Float,
+        Sf::Rg32Uint => Tf::Rg32Uint,
+        Sf::Rg32Sint => Tf::Rg32Sint,
+        Sf::Rg32Float => Tf::Rg32Float,
+        Sf::Rgba16Uint => Tf::Rgba16Uint,
+        Sf::Rgba16Sint => Tf::Rgba16Sint,
+        Sf::Rgba16Float => Tf::Rgba16Float,
+        Sf::Rgba32Uint => Tf::Rgba32Uint,
+        Sf::Rgba32Sint => Tf::Rgba32Sint,
+        Sf::Rgba32Float => Tf::Rgba32Float,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shader::{EmbeddedAssetSource, ShaderPreprocessor};
+    use wgpu::ShaderStages;
+
+    const SHADER: &str = r#"struct Globals { mvp: mat4x4<f32>, tint: vec4<f32> };
+@group(0) @binding(0) var<uniform> globals: Globals;
+@group(0) @binding(1) var<storage, read> input: array<f32>;
+@group(0) @binding(2) var<storage, read_write> output: array<f32>;
+@group(0) @binding(3) var color_tex: texture_2d<f32>;
+@group(0) @binding(4) var layers: texture_2d_array<i32>;
+@group(0) @binding(5) var depth_tex: texture_depth_2d;
+@group(0) @binding(6) var tex_sampler: sampler;
+@group(1) @binding(0) var out_tex: texture_storage_2d<rgba16float, write>;
+@group(1) @binding(1) var unused_cube: texture_cube<f32>;
+
+@vertex
+fn vs_main(@builtin(vertex_index) i: u32) -> @builtin(position) vec4<f32> {
+    return globals.mvp * vec4<f32>(f32(i), 0.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    let color = textureSample(color_tex, tex_sampler, vec2<f32>(0.5, 0.5));
+    let layer = textureLoad(layers, vec2<i32>(0, 0), 1, 0);
+    let depth = textureLoad(depth_tex, vec2<i32>(0, 0), 0);
+    return color * globals.tint + vec4<f32>(f32(layer.x) + depth, 0.0, 0.0, 0.0);
+}
+
+@compute @workgroup_size(8, 4, 1)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    output[id.x] = input[id.x];
+    textureStore(out_tex, vec2<i32>(i32(id.x), i32(id.y)), globals.tint);
+}
+"#;
+
+    fn reflection() -> ShaderReflection {
+        let shader = ShaderPreprocessor::from_source(EmbeddedAssetSource::new(&[("reflect.wgsl", SHADER)]))
+            .process("reflect")
+            .unwrap();
+        ShaderReflection::new(&shader).unwrap()
+    }
+
+    #[test]
+    fn reflects_entry_points() {
+        let reflection = reflection();
+        let entry_points: Vec<(&str, ShaderStages, [u32; 3])> =
+            reflection.entry_points.iter().map(|ep| (ep.name.as_str(), ep.stage, ep.workgroup_size)).collect();
+        assert_eq!(
+            entry_points,
+            [
+                ("vs_main", ShaderStages::VERTEX, [0, 0, 0]),
+                ("fs_main", ShaderStages::FRAGMENT, [0, 0, 0]),
+                ("cs_main", ShaderStages::COMPUTE, [8, 4, 1])
+            ]
+        );
+        assert_eq!(reflection.entry_point("cs_main").unwrap().workgroup_size, [8, 4, 1]);
+        assert!(reflection.entry_point("main").is_none());
+    }
+
+    #[test]
+    fn reflects_binding_types() {
+        let reflection = reflection();
+        let ty = |group, binding| reflection.binding(group, binding).unwrap().ty;
+        assert_eq!(
+            ty(0, 0),
+            wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: wgpu::BufferSize::new(80),
+            }
+        );
+        for (binding, read_only) in [(1, true), (2, false)] {
+            assert_eq!(
+                ty(0, binding),
+                wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                }
+            );
+        }
+        assert_eq!(
+            ty(0, 3),
+            wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            }
+        );
+        assert_eq!(
+            ty(0, 4),
+            wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Sint,
+                view_dimension: wgpu::TextureViewDimension::D2Array,
+                multisampled: false,
+            }
+        );
+        assert_eq!(
+            ty(0, 5),
+            wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Depth,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            }
+        );
+        assert_eq!(ty(0, 6), wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering));
+        assert_eq!(
+            ty(1, 0),
+            wgpu::BindingType::StorageTexture {
+                access: wgpu::StorageTextureAccess::WriteOnly,
+                format: wgpu::TextureFormat::Rgba16Float,
+                view_dimension: wgpu::TextureViewDimension::D2,
+            }
+        );
+        assert!(matches!(
+            ty(1, 1),
+            wgpu::BindingType::Texture { view_dimension: wgpu::TextureViewDimension::Cube, .. }
+        ));
+        assert_eq!(reflection.binding_by_name("depth_tex").unwrap().binding, 5);
+        assert_eq!(reflection.group_bindings(1).count(), 2);
+        let location = reflection.binding(0, 2).unwrap().location.as_ref().unwrap();
+        assert_eq!(location.to_string(), "reflect.wgsl:4");
+    }
+
+    #[test]
+    fn reflects_visibility_per_stage() {
+        let reflection = reflection();
+        let visibility = |group, binding| reflection.binding(group, binding).unwrap().visibility;
+        assert_eq!(visibility(0, 0), ShaderStages::VERTEX | ShaderStages::FRAGMENT | ShaderStages::COMPUTE);
+        assert_eq!(visibility(0, 1), ShaderStages::COMPUTE);
+        assert_eq!(visibility(0, 2), ShaderStages::COMPUTE);
+        for binding in 3..=6 {
+            assert_eq!(visibility(0, binding), ShaderStages::FRAGMENT);
+        }
+        assert_eq!(visibility(1, 0), ShaderStages::COMPUTE);
+        assert!(visibility(1, 1).is_empty());
+    }
+
+    #[test]
+    fn checks_visibilities() {
+        let reflection = reflection();
+        let all = ShaderStages::VERTEX | ShaderStages::FRAGMENT | ShaderStages::COMPUTE;
+        let mut visibilities = vec![all, ShaderStages::COMPUTE, ShaderStages::COMPUTE];
+        visibilities.extend([ShaderStages::FRAGMENT; 4]);
+        reflection.check_visibilities(0, &visibilities).unwrap();
+        // 没有被使用的 unused_cube 不需要提供 visibility
+        reflection.check_visibilities(1, &[ShaderStages::COMPUTE]).unwrap();
+
+        visibilities[0] = ShaderStages::VERTEX_FRAGMENT;
+        match reflection.check_visibilities(0, &visibilities) {
+            Err(ShaderError::BindingMismatch { group: 0, binding: 0, message, location, .. }) => {
+                assert!(message.contains("does not include"), "{message}");
+                assert_eq!(location.unwrap().line, 2);
+            }
+            other => panic!("expected a visibility mismatch, got {:?}", other),
+        }
+        match reflection.check_visibilities(0, &[all]) {
+            Err(ShaderError::BindingMismatch { binding: 1, message, .. }) => {
+                assert_eq!(message, "no visibility was provided")
+            }
+            other => panic!("expected a missing visibility, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use super::{PreprocessedShader, ShaderError, ShaderPreprocessor};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+use wgpu::ShaderModule;
+
+/// 桌面端的着色器热重载
+///
+/// 定时检查着色器文件及其所有 `#include` 文件的修改时间，有变化时重新预处理并创建 `ShaderModule`。
+/// 新的代码有错误时继续使用上一个可用的 module，错误由 [`ShaderWatcher::last_error`] 获取：
+/// ```ignore
+/// let mut watcher = ShaderWatcher::new(device, "fluid/collide", None)?;
+/// // 每一帧
+/// if watcher.reloaded(device) {
+///     compute_node.update_shader_module(device, watcher.module());
+/// }
+/// ```
+pub struct ShaderWatcher {
+    preprocessor: ShaderPreprocessor,
+    shader_name: String,
+    label: Option<String>,
+    module: ShaderModule,
+    shader: PreprocessedShader,
+    mtimes: Vec<(PathBuf, Option<SystemTime>)>,
+    interval: Duration,
+    last_poll: Instant,
+    last_error: Option<ShaderError>,
+}
+
+#[allow(dead_code)]
+impl ShaderWatcher {
+    /// 使用与 `create_shader_module` 相同的着色器目录
+    pub fn new(device: &wgpu::Device, shader_name: &str, label: Option<&str>) -> Result<Self, ShaderError> {
+        Self::with_preprocessor(device, super::default_preprocessor(), &super::platform_shader_name(shader_name), label)
+    }
+
+    /// `preprocessor` 的 `AssetSource` 需要能提供修改时间，否则永远不会重载
+    pub fn with_preprocessor(
+        device: &wgpu::Device, preprocessor: ShaderPreprocessor, shader_name: &str, label: Option<&str>,
+    ) -> Result<Self, ShaderError> {
+        let shader = preprocessor.process(shader_name)?;
+        let module = super::create_shader_module_from_preprocessed(device, &shader, label)?;
+        let mut watcher = ShaderWatcher {
+            preprocessor,
+            shader_name: shader_name.to_string(),
+            label: label.map(|l| l.to_string()),
+            module,
+            shader,
+            mtimes: vec![],
+            interval: Duration::from_millis(500),
+            last_poll: Instant::now(),
+            last_error: None,
+        };
+        watcher.mtimes = watcher.current_mtimes();
+        Ok(watcher)
+    }
+
+    /// 两次检查文件修改时间的最小间隔，默认 500 毫秒
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// 当前可用的 module
+    pub fn module(&self) -> &ShaderModule {
+        &self.module
+    }
+
+    /// 当前 module 对应的预处理结果，可用于重新反射绑定信息
+    pub fn shader(&self) -> &PreprocessedShader {
+        &self.shader
+    }
+
+    /// 最近一次重载失败的原因，重载成功后清空
+    pub fn last_error(&self) -> Option<&ShaderError> {
+        self.last_error.as_ref()
+    }
+
+    /// 检查文件是否有修改，成功创建了新的 module 时返回 `true`，
+    /// 此时需要用 [`ShaderWatcher::module`] 重建 pipeline
+    pub fn reloaded(&mut self, device: &wgpu::Device) -> bool {
+        if self.last_poll.elapsed() < self.interval {
+            return false;
+        }
+        self.last_poll = Instant::now();
+
+        let mtimes = self.current_mtimes();
+        if mtimes == self.mtimes {
+            return false;
+        }
+        // 不论成功与否都记录下来，避免对同一个错误反复报告
+        self.mtimes = mtimes;
+
+        let result = self.preprocessor.process(&self.shader_name).and_then(|shader| {
+            let module = super::create_shader_module_from_preprocessed(device, &shader, self.label.as_deref())?;
+            Ok((shader, module))
+        });
+        match result {
+            Ok((shader, module)) => {
+                self.shader = shader;
+                self.module = module;
+                self.last_error = None;
+                // include 关系可能发生了变化
+                self.mtimes = self.current_mtimes();
+                true
+            }
+            Err(e) => {
+                self.last_error = Some(e);
+                false
+            }
+        }
+    }
+
+    fn current_mtimes(&self) -> Vec<(PathBuf, Option<SystemTime>)> {
+        let mut files: Vec<PathBuf> = self.shader.source_map.files().map(|f| f.to_path_buf()).collect();
+        let top_level = PathBuf::from(format!("{}.wgsl", self.shader_name));
+        if !files.contains(&top_level) {
+            files.push(top_level);
+        }
+        files.sort();
+        files
+            .into_iter()
+            .map(|file| {
+                let mtime = self.preprocessor.source().modified(&file);
+                (file, mtime)
+            })
+            .collect()
+    }
+}
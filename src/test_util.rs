@@ -0,0 +1,26 @@
+//! 测试用的辅助函数
+
+/// 不带 surface 的 device，没有可用的 GPU 时 panic。
+/// 依赖 GPU 的测试需要标记 `#[ignore]`，在有 GPU 的机器上用 `cargo test -- --ignored` 运行
+pub(crate) fn headless_device() -> (wgpu::Device, wgpu::Queue) {
+    let instance = wgpu::Instance::new(wgpu::Backends::all());
+    let adapter = block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))
+        .expect("no GPU adapter is available");
+    block_on(adapter.request_device(&wgpu::DeviceDescriptor::default(), None)).unwrap()
+}
+
+// 原生平台上 wgpu 返回的 future 不需要外部驱动，循环 poll 即可
+pub(crate) fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    struct NoopWake;
+    impl std::task::Wake for NoopWake {
+        fn wake(self: std::sync::Arc<Self>) {}
+    }
+    let waker = std::task::Waker::from(std::sync::Arc::new(NoopWake));
+    let mut cx = std::task::Context::from_waker(&waker);
+    let mut future = Box::pin(future);
+    loop {
+        if let std::task::Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use crate::BufferObj;
+use bytemuck::Pod;
+use std::marker::PhantomData;
+use std::ops::{Deref, Range};
+
+/// buffer 读写错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BufferError {
+    /// 写入的元素范围超出了 buffer 的长度
+    OutOfBounds { offset: usize, count: usize, len: usize },
+    /// `write_range` 的范围长度与数据长度不一致
+    LengthMismatch { range: Range<usize>, data_len: usize },
+    /// 写入的字节偏移或大小不是 `wgpu::COPY_BUFFER_ALIGNMENT` 的整数倍
+    Misaligned { offset: wgpu::BufferAddress, size: wgpu::BufferAddress },
+    /// buffer 缺少所需的 usage，如回读需要 `COPY_SRC`
+    MissingUsage(wgpu::BufferUsages),
+    /// 不支持回读的纹理格式，如压缩格式
+    UnsupportedFormat(wgpu::TextureFormat),
+    /// `map_async` 失败，通常是 device 已丢失
+    MapFailed,
+    /// 当前帧的剩余空间不足
+    FrameOverflow { requested: wgpu::BufferAddress, remaining: wgpu::BufferAddress },
+    /// 单次分配超过了动态绑定的窗口大小
+    AllocationTooLarge { size: wgpu::BufferAddress, binding_size: wgpu::BufferAddress },
+    /// 下一帧的区域仍在被 GPU 使用，`frame` 为该区域的序号
+    FrameInFlight { frame: u32 },
+}
+
+impl std::fmt::Display for BufferError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BufferError::OutOfBounds { offset, count, len } => {
+                write!(f, "Writing {count} elements at offset {offset} exceeds the buffer length {len}")
+            }
+            BufferError::LengthMismatch { range, data_len } => {
+                write!(f, "Range {range:?} has {} elements, but {data_len} were provided", range.len())
+            }
+            BufferError::Misaligned { offset, size } => {
+                write!(f, "Byte offset {offset} and size {size} must be multiples of {}", wgpu::COPY_BUFFER_ALIGNMENT)
+            }
+            BufferError::MissingUsage(usage) => write!(f, "Buffer was not created with {:?}", usage),
+            BufferError::UnsupportedFormat(format) => write!(f, "Reading back {:?} textures is not supported", format),
+            BufferError::MapFailed => write!(f, "Unable to map the staging buffer"),
+            BufferError::FrameOverflow { requested, remaining } => {
+                write!(f, "Allocating {requested} bytes exceeds the {remaining} bytes left in this frame")
+            }
+            BufferError::AllocationTooLarge { size, binding_size } => {
+                write!(f, "Allocation of {size} bytes exceeds the binding size {binding_size}")
+            }
+            BufferError::FrameInFlight { frame } => {
+                write!(f, "Frame region {frame} is still in use by the GPU")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BufferError {}
+
+/// 记住元素类型与个数的 buffer
+///
+/// 通过 `Deref` 或 [`TypedBuffer::buffer_obj`] 得到 `BufferObj`，用于 `BindingGroupSetting` 等
+pub struct TypedBuffer<T: Pod> {
+    obj: BufferObj,
+    len: usize,
+    _marker: PhantomData<T>,
+}
+
+#[allow(dead_code)]
+impl<T: Pod> TypedBuffer<T> {
+    pub fn new(device: &wgpu::Device, slice: &[T], usage: wgpu::BufferUsages, label: Option<&'static str>) -> Self {
+        let obj = BufferObj::create_buffer(device, Some(slice), None, usage, label);
+        TypedBuffer { obj, len: slice.len(), _marker: PhantomData }
+    }
+
+    /// 只有一个元素的 buffer，如 uniform
+    pub fn from_item(device: &wgpu::Device, item: &T, usage: wgpu::BufferUsages, label: Option<&'static str>) -> Self {
+        let obj = BufferObj::create_buffer(device, None, Some(item), usage, label);
+        TypedBuffer { obj, len: 1, _marker: PhantomData }
+    }
+
+    /// 可容纳 `len` 个元素、内容为 0 的 buffer
+    pub fn empty(device: &wgpu::Device, len: usize, usage: wgpu::BufferUsages, label: Option<&'static str>) -> Self {
+        let size = (std::mem::size_of::<T>() * len) as wgpu::BufferAddress;
+        let obj = BufferObj::create_empty_buffer(device, size, usage, label);
+        TypedBuffer { obj, len, _marker: PhantomData }
+    }
+
+    pub fn create_uniform_buffer(device: &wgpu::Device, uniform: &T, label: Option<&'static str>) -> Self {
+        Self::from_item(device, uniform, wgpu::BufferUsages::UNIFORM, label)
+    }
+
+    pub fn create_storage_buffer(device: &wgpu::Device, slice: &[T], label: Option<&'static str>) -> Self {
+        Self::new(device, slice, wgpu::BufferUsages::STORAGE, label)
+    }
+
+    /// 元素个数
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn buffer_obj(&self) -> &BufferObj {
+        &self.obj
+    }
+
+    pub fn into_buffer_obj(self) -> BufferObj {
+        self.obj
+    }
+
+    /// 写入第 0 个元素
+    pub fn write(&self, queue: &wgpu::Queue, item: &T) -> Result<(), BufferError> {
+        self.write_slice(queue, 0, std::slice::from_ref(item))
+    }
+
+    /// 从第 `offset` 个元素开始写入 `data`
+    pub fn write_slice(&self, queue: &wgpu::Queue, offset: usize, data: &[T]) -> Result<(), BufferError> {
+        let bytes = write_bytes_range(self.len, std::mem::size_of::<T>(), offset, data.len())?;
+        if !bytes.is_empty() {
+            queue.write_buffer(&self.obj.buffer, bytes.start, bytemuck::cast_slice(data));
+        }
+        Ok(())
+    }
+
+    /// 用 `data` 替换 `range` 范围内的元素，`data` 的长度必须与范围一致
+    pub fn write_range(&self, queue: &wgpu::Queue, range: Range<usize>, data: &[T]) -> Result<(), BufferError> {
+        if range.len() != data.len() {
+            return Err(BufferError::LengthMismatch { range, data_len: data.len() });
+        }
+        self.write_slice(queue, range.start, data)
+    }
+}
+
+impl<T: Pod> Deref for TypedBuffer<T> {
+    type Target = BufferObj;
+    fn deref(&self) -> &BufferObj {
+        &self.obj
+    }
+}
+
+// 检查在长度为 `len` 的 buffer 中从第 `offset` 个元素开始写入 `count` 个元素是否合法，返回写入的字节范围
+fn write_bytes_range(
+    len: usize, element_size: usize, offset: usize, count: usize,
+) -> Result<Range<wgpu::BufferAddress>, BufferError> {
+    if offset.checked_add(count).map_or(true, |end| end > len) {
+        return Err(BufferError::OutOfBounds { offset, count, len });
+    }
+    let element_size = element_size as wgpu::BufferAddress;
+    let byte_offset = offset as wgpu::BufferAddress * element_size;
+    let byte_size = count as wgpu::BufferAddress * element_size;
+    if count == 0 {
+        return Ok(byte_offset..byte_offset);
+    }
+    if byte_offset % wgpu::COPY_BUFFER_ALIGNMENT != 0 || byte_size % wgpu::COPY_BUFFER_ALIGNMENT != 0 {
+        return Err(BufferError::Misaligned { offset: byte_offset, size: byte_size });
+    }
+    Ok(byte_offset..byte_offset + byte_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_write_bounds() {
+        assert_eq!(write_bytes_range(8, 4, 0, 8), Ok(0..32));
+        assert_eq!(write_bytes_range(8, 4, 6, 2), Ok(24..32));
+        assert_eq!(write_bytes_range(8, 4, 8, 0), Ok(32..32));
+        assert_eq!(write_bytes_range(8, 4, 7, 2), Err(BufferError::OutOfBounds { offset: 7, count: 2, len: 8 }));
+        assert_eq!(write_bytes_range(8, 4, 9, 0), Err(BufferError::OutOfBounds { offset: 9, count: 0, len: 8 }));
+        // offset + count 溢出时不能回绕成一个合法的范围
+        assert_eq!(
+            write_bytes_range(8, 4, usize::MAX, 2),
+            Err(BufferError::OutOfBounds { offset: usize::MAX, count: 2, len: 8 })
+        );
+    }
+
+    #[test]
+    fn checks_write_alignment() {
+        // 元素大小为 2 字节时，偏移与大小都要是 4 字节的整数倍
+        assert_eq!(write_bytes_range(8, 2, 2, 4), Ok(4..12));
+        assert_eq!(write_bytes_range(8, 2, 1, 2), Err(BufferError::Misaligned { offset: 2, size: 4 }));
+        assert_eq!(write_bytes_range(8, 2, 2, 3), Err(BufferError::Misaligned { offset: 4, size: 6 }));
+        // 空写入不需要对齐
+        assert_eq!(write_bytes_range(8, 2, 1, 0), Ok(2..2));
+    }
+
+    #[test]
+    #[ignore = "requires a GPU adapter"]
+    fn reports_write_errors() {
+        let (device, queue) = crate::test_util::headless_device();
+        let buffer =
+            TypedBuffer::<u32>::empty(&device, 4, wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST, None);
+        buffer.write_slice(&queue, 2, &[1, 2]).unwrap();
+        assert_eq!(
+            buffer.write_slice(&queue, usize::MAX, &[1]),
+            Err(BufferError::OutOfBounds { offset: usize::MAX, count: 1, len: 4 })
+        );
+        assert_eq!(
+            buffer.write_range(&queue, 0..3, &[1, 2]),
+            Err(BufferError::LengthMismatch { range: 0..3, data_len: 2 })
+        );
+        buffer.write_range(&queue, 1..3, &[1, 2]).unwrap();
+    }
+}